@@ -0,0 +1,194 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use node_semver::Version;
+
+use crate::{
+    requests::GetBaseTreeResponse,
+    versioning::{UpdatedExtensions, Versioning},
+};
+
+// Newest versions first, versions which can not be parsed are sorted last
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (a.parse::<Version>(), b.parse::<Version>()) {
+        (Ok(a), Ok(b)) => b.cmp(&a),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+// Copies the files of every updated source into its archive and removes the archived
+// versions exceeding the retention, the published version itself is always kept.
+// Returns the archived files and the paths of the files to remove from the registry.
+pub fn update(
+    registry_versioning: &mut Versioning,
+    registry_tree: &GetBaseTreeResponse,
+    updated_extensions: &UpdatedExtensions,
+    retention: usize,
+) -> (HashMap<String, String>, Vec<String>) {
+    let mut archived_files = HashMap::new();
+    let mut removed_files = vec![];
+
+    if registry_tree.truncated {
+        eprintln!("The registry tree was truncated by GitHub, archived versions exceeding the retention are not removed");
+    }
+
+    for (id, files) in updated_extensions.iter() {
+        let Some(version) = registry_versioning
+            .source(id)
+            .map(|source| source.version().to_string())
+        else {
+            continue;
+        };
+
+        let prefix = format!("{}/", id);
+        let archive_prefix = format!("{}/versions/", id);
+
+        for (path, content) in files.iter() {
+            if let Some(relative_path) = path.strip_prefix(&prefix) {
+                archived_files.insert(
+                    format!("{}{}/{}", archive_prefix, version, relative_path),
+                    content.clone(),
+                );
+            }
+        }
+
+        let mut versions = vec![version.clone()];
+
+        for file in registry_tree.tree.iter() {
+            if let Some((archived_version, _)) = file
+                .path
+                .strip_prefix(&archive_prefix)
+                .and_then(|path| path.split_once('/'))
+            {
+                if !versions.iter().any(|version| version == archived_version) {
+                    versions.push(archived_version.to_string());
+                }
+            }
+        }
+
+        versions.sort_by(|a, b| compare_versions(a, b));
+
+        let mut kept_versions = vec![];
+        let mut kept_others = 0;
+
+        for archived_version in versions {
+            if archived_version == version {
+                kept_versions.push(archived_version);
+            } else if kept_others + 1 < retention {
+                kept_others += 1;
+                kept_versions.push(archived_version);
+            } else if !registry_tree.truncated {
+                let version_prefix = format!("{}{}/", archive_prefix, archived_version);
+
+                removed_files.extend(
+                    registry_tree
+                        .tree
+                        .iter()
+                        .filter(|file| {
+                            file._type == "blob" && file.path.starts_with(&version_prefix)
+                        })
+                        .map(|file| file.path.clone()),
+                );
+
+                println!("{}: Removing {} from the archive", id, archived_version);
+            } else {
+                kept_versions.push(archived_version);
+            }
+        }
+
+        println!(
+            "{}: Archived {} ({} versions kept)",
+            id,
+            version,
+            kept_versions.len()
+        );
+
+        registry_versioning.set_versions(id, kept_versions);
+    }
+
+    (archived_files, removed_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{source, tree, updated_extensions, versioning};
+
+    fn registry_versioning(version: &str) -> Versioning {
+        versioning(vec![source("Example", version)])
+    }
+
+    fn bundle() -> UpdatedExtensions {
+        updated_extensions(&[("Example", &[("index.js", b"bundle")])])
+    }
+
+    fn kept_versions(versioning: &Versioning) -> serde_json::Value {
+        serde_json::to_value(versioning).unwrap()["sources"][0]["versions"].clone()
+    }
+
+    #[test]
+    fn the_oldest_versions_exceeding_the_retention_are_removed() {
+        let mut registry_versioning = registry_versioning("1.3.0");
+        let registry_tree = tree(&[
+            ("Example/index.js", ""),
+            ("Example/versions/1.0.0/index.js", ""),
+            ("Example/versions/1.1.0/index.js", ""),
+            ("Example/versions/1.2.0/index.js", ""),
+            ("Example/versions/1.2.0/static/icon.png", ""),
+        ]);
+
+        let (archived_files, mut removed_files) =
+            update(&mut registry_versioning, &registry_tree, &bundle(), 2);
+
+        removed_files.sort();
+
+        assert_eq!(
+            archived_files.keys().collect::<Vec<&String>>(),
+            vec!["Example/versions/1.3.0/index.js"]
+        );
+        assert_eq!(
+            removed_files,
+            vec![
+                "Example/versions/1.0.0/index.js",
+                "Example/versions/1.1.0/index.js"
+            ]
+        );
+        assert_eq!(
+            kept_versions(&registry_versioning),
+            serde_json::json!(["1.3.0", "1.2.0"])
+        );
+    }
+
+    #[test]
+    fn the_published_version_is_kept_with_a_retention_of_one() {
+        let mut registry_versioning = registry_versioning("1.0.0");
+        let registry_tree = tree(&[("Example/versions/2.0.0/index.js", "")]);
+
+        let (_, removed_files) = update(&mut registry_versioning, &registry_tree, &bundle(), 1);
+
+        assert_eq!(removed_files, vec!["Example/versions/2.0.0/index.js"]);
+        assert_eq!(
+            kept_versions(&registry_versioning),
+            serde_json::json!(["1.0.0"])
+        );
+    }
+
+    #[test]
+    fn nothing_is_removed_from_a_truncated_tree() {
+        let mut registry_versioning = registry_versioning("1.2.0");
+        let mut registry_tree = tree(&[
+            ("Example/versions/1.0.0/index.js", ""),
+            ("Example/versions/1.1.0/index.js", ""),
+        ]);
+        registry_tree.truncated = true;
+
+        let (_, removed_files) = update(&mut registry_versioning, &registry_tree, &bundle(), 1);
+
+        assert!(removed_files.is_empty());
+        assert_eq!(
+            kept_versions(&registry_versioning),
+            serde_json::json!(["1.2.0", "1.1.0", "1.0.0"])
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::env;
+
+use base64::prelude::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::versioning::{Outcome, SourceChange};
+
+// The oldest entries are dropped beyond this amount, the registry history itself
+// still contains every publish
+const MAX_ENTRIES: usize = 500;
+
+// The registry keeps a history of every publish in changelog.json, so consumers
+// can show what changed without walking the commit history of the registry.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Changelog {
+    entries: Vec<ChangelogEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangelogEntry {
+    timestamp: String,
+    repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+    sources: Vec<ChangelogSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangelogSource {
+    id: String,
+    name: String,
+    change: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    to: String,
+}
+
+// Markdown also generates CHANGELOG.md next to changelog.json
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangelogFormat {
+    None,
+    Json,
+    Markdown,
+}
+
+impl Changelog {
+    // Adds the published changes as the newest entry, only the newest MAX_ENTRIES are kept
+    pub fn record(&mut self, repository: &str, changes: &[SourceChange]) {
+        let sources = changes
+            .iter()
+            .filter(|change| change.is_published())
+            .map(|change| ChangelogSource {
+                id: change.id.clone(),
+                name: change.name.clone(),
+                change: match change.outcome {
+                    Outcome::Added => "added",
+                    Outcome::Downgraded { .. } => "downgraded",
+                    Outcome::MetadataUpdated => "metadata",
+                    _ => "updated",
+                }
+                .to_string(),
+                from: change.old_version.clone(),
+                to: change.new_version.clone(),
+            })
+            .collect();
+
+        self.entries.insert(
+            0,
+            ChangelogEntry {
+                timestamp: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                repository: repository.to_string(),
+                commit: env::var("GITHUB_SHA").ok(),
+                sources,
+            },
+        );
+
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn to_base64(&self) -> Result<String, u8> {
+        match serde_json::to_vec_pretty(&self) {
+            Ok(mut buffer) => {
+                buffer.push(b'\n');
+
+                println!("Encoded the changelog file to base64");
+
+                Ok(BASE64_STANDARD.encode(&buffer))
+            }
+            Err(err) => {
+                eprintln!(
+                    "An error occurred while serializing the changelog struct: {}",
+                    &err
+                );
+                Err(0x1)
+            }
+        }
+    }
+
+    // The markdown page is regenerated from all entries on every publish
+    pub fn to_markdown_base64(&self) -> String {
+        let mut markdown = "# Changelog\n".to_string();
+
+        for entry in self.entries.iter() {
+            markdown.push_str(&format!(
+                "\n## {} — {}\n\n",
+                entry.timestamp, entry.repository
+            ));
+
+            if let Some(commit) = &entry.commit {
+                markdown.push_str(&format!("Source commit: `{}`\n\n", commit));
+            }
+
+            for source in entry.sources.iter() {
+                let transition = match &source.from {
+                    Some(from) if *from != source.to => format!("{} → {}", from, source.to),
+                    _ => source.to.clone(),
+                };
+
+                markdown.push_str(&format!(
+                    "- **{}** (`{}`): {} ({})\n",
+                    source.name, source.id, transition, source.change
+                ));
+            }
+        }
+
+        BASE64_STANDARD.encode(&markdown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::change;
+
+    #[test]
+    fn record_adds_the_published_changes_as_the_newest_entry() {
+        let mut changelog = Changelog::default();
+
+        changelog.record(
+            "paperback-community/first",
+            &[change("A", None, "1.0.0", Outcome::Added)],
+        );
+        changelog.record(
+            "paperback-community/second",
+            &[
+                change("A", Some("1.0.0"), "1.1.0", Outcome::Updated),
+                change(
+                    "B",
+                    Some("2.0.0"),
+                    "1.0.0",
+                    Outcome::Downgraded { allowed: true },
+                ),
+                change("C", Some("1.0.0"), "1.0.0", Outcome::Unchanged),
+                change(
+                    "D",
+                    Some("1.0.0"),
+                    "1.0.0",
+                    Outcome::MetadataDrift { blocked: false },
+                ),
+            ],
+        );
+
+        assert_eq!(changelog.entries.len(), 2);
+        assert_eq!(
+            changelog.entries[0].repository,
+            "paperback-community/second"
+        );
+        assert_eq!(
+            changelog.entries[0]
+                .sources
+                .iter()
+                .map(|source| (source.id.as_str(), source.change.as_str()))
+                .collect::<Vec<(&str, &str)>>(),
+            vec![("A", "updated"), ("B", "downgraded")]
+        );
+        assert_eq!(changelog.entries[1].sources[0].change, "added");
+        assert_eq!(changelog.entries[1].sources[0].from, None);
+    }
+
+    #[test]
+    fn record_keeps_only_the_newest_entries() {
+        let mut changelog = Changelog::default();
+
+        for index in 0..MAX_ENTRIES + 5 {
+            changelog.record(
+                &format!("paperback-community/{}", index),
+                &[change("A", None, "1.0.0", Outcome::Added)],
+            );
+        }
+
+        assert_eq!(changelog.entries.len(), MAX_ENTRIES);
+        assert_eq!(
+            changelog.entries[0].repository,
+            format!("paperback-community/{}", MAX_ENTRIES + 4)
+        );
+    }
+
+    #[test]
+    fn markdown_lists_every_entry() {
+        let mut changelog = Changelog::default();
+
+        changelog.record(
+            "paperback-community/extensions",
+            &[
+                change("A", Some("1.0.0"), "1.1.0", Outcome::Updated),
+                change("B", Some("1.0.0"), "1.0.0", Outcome::MetadataUpdated),
+            ],
+        );
+
+        let markdown = String::from_utf8(
+            BASE64_STANDARD
+                .decode(changelog.to_markdown_base64())
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(markdown.starts_with("# Changelog\n"));
+        assert!(markdown.contains("— paperback-community/extensions\n"));
+        assert!(markdown.contains("- **A** (`A`): 1.0.0 → 1.1.0 (updated)\n"));
+        assert!(markdown.contains("- **B** (`B`): 1.0.0 (metadata)\n"));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::requests::{GetBaseTreeResponse, ResponseFile};
+
+// Every channel is a separate registry within the registry repository, the stable
+// channel is stored at the root and the other channels in a directory of their name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 3] = [Channel::Stable, Channel::Beta, Channel::Nightly];
+
+    pub fn from_name(name: &str) -> Option<Channel> {
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.name() == name)
+    }
+
+    // The channel is the prefix of the branch, e.g. "beta/<paperback_semver>"
+    pub fn from_branch(branch: &str) -> Option<Channel> {
+        branch
+            .split_once('/')
+            .and_then(|(prefix, _)| Channel::from_name(prefix))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+
+    pub fn allows_prerelease(&self) -> bool {
+        !matches!(self, Channel::Stable)
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Channel::Stable => "",
+            Channel::Beta => "beta/",
+            Channel::Nightly => "nightly/",
+        }
+    }
+
+    // Returns the path in the registry repository of a path within the channel
+    pub fn path(&self, path: &str) -> String {
+        format!("{}{}", self.prefix(), path)
+    }
+
+    // Returns the part of a recursive registry tree which belongs to the channel,
+    // with the paths relative to the channel directory
+    pub fn tree(&self, registry_tree: &GetBaseTreeResponse) -> GetBaseTreeResponse {
+        let other_prefixes: Vec<&str> = Channel::ALL
+            .into_iter()
+            .filter(|channel| channel != self && !channel.prefix().is_empty())
+            .map(|channel| channel.prefix())
+            .collect();
+
+        let tree = registry_tree
+            .tree
+            .iter()
+            .filter_map(|file| {
+                let path = file.path.strip_prefix(self.prefix())?;
+
+                if path.is_empty()
+                    || (self.prefix().is_empty()
+                        && other_prefixes.iter().any(|prefix| {
+                            format!("{}/", path) == *prefix || path.starts_with(prefix)
+                        }))
+                {
+                    return None;
+                }
+
+                Some(ResponseFile {
+                    path: path.to_string(),
+                    ..file.clone()
+                })
+            })
+            .collect();
+
+        GetBaseTreeResponse {
+            sha: registry_tree.sha.clone(),
+            url: registry_tree.url.clone(),
+            truncated: registry_tree.truncated,
+            tree,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::tree;
+
+    fn paths(tree: &GetBaseTreeResponse) -> Vec<&str> {
+        tree.tree.iter().map(|file| file.path.as_str()).collect()
+    }
+
+    #[test]
+    fn channels_are_read_from_the_branch_prefix() {
+        assert_eq!(Channel::from_branch("beta/0.9"), Some(Channel::Beta));
+        assert_eq!(Channel::from_branch("nightly/0.9"), Some(Channel::Nightly));
+        assert_eq!(Channel::from_branch("0.9"), None);
+        assert_eq!(Channel::from_branch("feature/0.9"), None);
+    }
+
+    #[test]
+    fn every_channel_has_its_own_part_of_the_registry() {
+        let registry_tree = tree(&[
+            ("versioning.json", "a"),
+            ("Example/index.js", "b"),
+            ("beta", "c"),
+            ("beta/versioning.json", "d"),
+            ("nightly/Example/index.js", "e"),
+        ]);
+
+        assert_eq!(Channel::Stable.path("versioning.json"), "versioning.json");
+        assert_eq!(
+            Channel::Nightly.path("versioning.json"),
+            "nightly/versioning.json"
+        );
+        assert_eq!(
+            paths(&Channel::Stable.tree(&registry_tree)),
+            vec!["versioning.json", "Example/index.js"]
+        );
+        assert_eq!(
+            paths(&Channel::Beta.tree(&registry_tree)),
+            vec!["versioning.json"]
+        );
+        assert_eq!(
+            paths(&Channel::Nightly.tree(&registry_tree)),
+            vec!["Example/index.js"]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::env;
+
+use crate::{
+    channel::Channel,
+    versioning::{Outcome, SourceChange},
+};
+
+// The default layout of the commit message, a registry can provide its own
+// template with the same placeholders through the publish.commit_template setting.
+const DEFAULT_TEMPLATE: &str = "{summary}\n\n{changes}\n\n{trailers}";
+
+pub fn generate(
+    repository: &str,
+    channel: Channel,
+    changes: &[SourceChange],
+    template: Option<&str>,
+) -> String {
+    let published: Vec<&SourceChange> = changes
+        .iter()
+        .filter(|change| change.is_published())
+        .collect();
+
+    let summary = format!(
+        "Update {} {} from {}",
+        published.len(),
+        if published.len() == 1 {
+            "source"
+        } else {
+            "sources"
+        },
+        repository
+    );
+
+    let change_lines: Vec<String> = published
+        .iter()
+        .map(|change| match (&change.old_version, change.outcome) {
+            (_, Outcome::MetadataUpdated) => {
+                format!("{}: {} (metadata only)", change.id, change.new_version)
+            }
+            (Some(old_version), _) => {
+                format!("{}: {} → {}", change.id, old_version, change.new_version)
+            }
+            (None, _) => format!("{}: {} (new)", change.id, change.new_version),
+        })
+        .collect();
+
+    let source_commit = env::var("GITHUB_SHA").ok();
+    let run_id = env::var("GITHUB_RUN_ID").ok();
+
+    let mut trailers = vec![format!("Source-Repository: {}", repository)];
+
+    if channel != Channel::Stable {
+        trailers.push(format!("Channel: {}", channel.name()));
+    }
+
+    if let Some(source_commit) = &source_commit {
+        trailers.push(format!("Source-Commit: {}", source_commit));
+    }
+
+    if let Some(run_id) = &run_id {
+        trailers.push(format!(
+            "Workflow-Run: {}/{}/actions/runs/{}",
+            env::var("GITHUB_SERVER_URL").unwrap_or("https://github.com".to_string()),
+            env::var("GITHUB_REPOSITORY").unwrap_or(repository.to_string()),
+            run_id
+        ));
+    }
+
+    let changes = change_lines.join("\n");
+    let trailers = trailers.join("\n");
+    let count = published.len().to_string();
+
+    render(
+        template.unwrap_or(DEFAULT_TEMPLATE),
+        |placeholder| match placeholder {
+            "summary" => Some(&summary),
+            "changes" => Some(&changes),
+            "trailers" => Some(&trailers),
+            "repository" => Some(repository),
+            "channel" => Some(channel.name()),
+            "count" => Some(&count),
+            "source_commit" => Some(source_commit.as_deref().unwrap_or("")),
+            "run_id" => Some(run_id.as_deref().unwrap_or("")),
+            _ => None,
+        },
+    )
+    .trim()
+    .to_string()
+}
+
+// Replaces the placeholders of the template in a single pass, so a placeholder within
+// a substituted value, e.g. in the name of a repository, is never replaced itself.
+// Unknown placeholders are kept as they are and an escaped \n is a line break.
+fn render<'a>(template: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut message = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '\\']) {
+        message.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("\\n") {
+            message.push('\n');
+            rest = after;
+            continue;
+        }
+
+        if let Some((placeholder, after)) = rest
+            .strip_prefix('{')
+            .and_then(|inner| inner.split_once('}'))
+        {
+            if let Some(value) = value(placeholder) {
+                message.push_str(value);
+                rest = after;
+                continue;
+            }
+        }
+
+        // Both characters are a single byte
+        message.push_str(&rest[..1]);
+        rest = &rest[1..];
+    }
+
+    message.push_str(rest);
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::change;
+
+    fn value(placeholder: &str) -> Option<&'static str> {
+        match placeholder {
+            "summary" => Some("Update {count} sources"),
+            "count" => Some("2"),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn replaces_every_placeholder_once() {
+        assert_eq!(
+            render("{summary} ({count})", value),
+            "Update {count} sources (2)"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_braces() {
+        assert_eq!(render("{unknown} {{count}} {", value), "{unknown} {2} {");
+    }
+
+    #[test]
+    fn converts_escaped_line_breaks() {
+        assert_eq!(render("{count}\\n\\n\\t", value), "2\n\n\\t");
+    }
+
+    #[test]
+    fn lists_the_published_changes() {
+        let changes = [
+            change("A", Some("1.0.0"), "1.1.0", Outcome::Updated),
+            change("B", None, "1.0.0", Outcome::Added),
+            change("C", Some("1.0.0"), "1.0.0", Outcome::MetadataUpdated),
+            change("D", Some("1.0.0"), "1.0.0", Outcome::Unchanged),
+        ];
+
+        assert_eq!(
+            generate(
+                "paperback-community/extensions",
+                Channel::Stable,
+                &changes,
+                Some("{summary}\\n\\n{changes}\\n{count} in {channel}"),
+            ),
+            "Update 3 sources from paperback-community/extensions\n\nA: 1.0.0 → 1.1.0\nB: 1.0.0 (new)\nC: 1.0.0 (metadata only)\n3 in stable"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::requests::GetBaseTreeResponse;
+
+// Only the files which are published to the registry are compared,
+// these are the bundle and the static files of every source.
+pub fn is_published_file(relative_path: &str) -> bool {
+    relative_path == "index.js" || relative_path.starts_with("static/")
+}
+
+// Groups the blob shas of a recursive tree by source id, the files
+// are keyed by their path relative to the source directory.
+pub fn source_blobs(tree: &GetBaseTreeResponse) -> HashMap<String, BTreeMap<String, String>> {
+    let mut source_blobs: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+
+    if tree.truncated {
+        eprintln!(
+            "The tree {} was truncated by GitHub, the content of some sources can not be compared",
+            &tree.sha
+        );
+    }
+
+    for file in tree.tree.iter() {
+        if file._type != "blob" {
+            continue;
+        }
+
+        let Some((id, relative_path)) = file.path.split_once('/') else {
+            continue;
+        };
+
+        if !is_published_file(relative_path) {
+            continue;
+        }
+
+        source_blobs
+            .entry(id.to_string())
+            .or_default()
+            .insert(relative_path.to_string(), file.sha.clone());
+    }
+
+    source_blobs
+}
+
+// Returns the ids of the sources which exist in both trees
+// but of which the published files are not identical.
+pub fn changed_sources(
+    repository_tree: &GetBaseTreeResponse,
+    registry_tree: &GetBaseTreeResponse,
+) -> HashSet<String> {
+    let repository_blobs = source_blobs(repository_tree);
+    let registry_blobs = source_blobs(registry_tree);
+
+    let changed_sources: HashSet<String> = repository_blobs
+        .iter()
+        .filter(|(id, blobs)| {
+            registry_blobs
+                .get(*id)
+                .is_some_and(|registry_blobs| registry_blobs != *blobs)
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    println!("Compared the content of the sources in both trees");
+
+    changed_sources
+}
+
+// Returns the registry files of a source which are not part of its published files,
+// these were removed from the repository since the previous version. The archive
+// of the source is kept.
+pub fn removed_files(
+    registry_tree: &GetBaseTreeResponse,
+    id: &str,
+    files: &HashMap<String, String>,
+) -> Vec<String> {
+    let prefix = format!("{}/", id);
+    let archive_prefix = format!("{}/versions/", id);
+
+    let removed_files: Vec<String> = registry_tree
+        .tree
+        .iter()
+        .filter(|file| {
+            file._type == "blob"
+                && file.path.starts_with(&prefix)
+                && !file.path.starts_with(&archive_prefix)
+                && !files.contains_key(&file.path)
+        })
+        .map(|file| file.path.clone())
+        .collect();
+
+    for path in removed_files.iter() {
+        println!(
+            "Removing {} from the registry, it no longer exists in the repository",
+            path
+        );
+    }
+
+    removed_files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::tree;
+
+    #[test]
+    fn sources_with_different_published_files_are_changed() {
+        let repository_tree = tree(&[
+            ("Changed/index.js", "a"),
+            ("Changed/static/icon.png", "b"),
+            ("Unchanged/index.js", "c"),
+            ("Unchanged/README.md", "d"),
+            ("New/index.js", "e"),
+        ]);
+        let registry_tree = tree(&[
+            ("Changed/index.js", "a"),
+            ("Changed/static/icon.png", "f"),
+            ("Unchanged/index.js", "c"),
+            ("Unchanged/versions/1.0.0/index.js", "g"),
+        ]);
+
+        assert_eq!(
+            changed_sources(&repository_tree, &registry_tree),
+            HashSet::from(["Changed".to_string()])
+        );
+    }
+
+    #[test]
+    fn sources_with_a_removed_static_file_are_changed() {
+        let repository_tree = tree(&[("Example/index.js", "a")]);
+        let registry_tree = tree(&[("Example/index.js", "a"), ("Example/static/icon.png", "b")]);
+
+        assert_eq!(
+            changed_sources(&repository_tree, &registry_tree),
+            HashSet::from(["Example".to_string()])
+        );
+    }
+
+    #[test]
+    fn removed_files_keep_the_archive() {
+        let registry_tree = tree(&[
+            ("Example/index.js", "a"),
+            ("Example/static/old.png", "b"),
+            ("Example/versions/1.0.0/static/old.png", "c"),
+            ("Other/index.js", "d"),
+        ]);
+        let files = HashMap::from([("Example/index.js".to_string(), String::new())]);
+
+        assert_eq!(
+            removed_files(&registry_tree, "Example", &files),
+            vec!["Example/static/old.png"]
+        );
+    }
+}
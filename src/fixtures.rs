@@ -0,0 +1,95 @@
+// The fixtures shared by the tests of the modules
+
+use std::collections::HashMap;
+
+use base64::prelude::*;
+use serde_json::{json, Value};
+
+use crate::{
+    requests::{GetBaseTreeResponse, ResponseFile},
+    versioning::{Outcome, SourceChange, UpdatedExtensions, Versioning},
+};
+
+// A registry entry as JSON, so a test can change its fields before parsing it
+pub fn source(id: &str, version: &str) -> Value {
+    json!({
+        "id": id,
+        "name": id,
+        "description": "An example source",
+        "version": version,
+        "icon": "icon.png",
+        "language": "en",
+        "contentRating": "EVERYONE",
+        "badges": [],
+        "capabilities": 1,
+        "developers": []
+    })
+}
+
+pub fn versioning(sources: Vec<Value>) -> Versioning {
+    serde_json::from_value(json!({
+        "buildTime": "2024-01-01T00:00:00.000Z",
+        "builtWith": { "toolchain": "0.9.0", "types": "0.9.0" },
+        "repository": { "name": "Example", "description": "An example repository" },
+        "sources": sources
+    }))
+    .unwrap()
+}
+
+// A tree of blobs by their path and sha
+pub fn tree(files: &[(&str, &str)]) -> GetBaseTreeResponse {
+    GetBaseTreeResponse {
+        sha: String::new(),
+        url: String::new(),
+        truncated: false,
+        tree: files
+            .iter()
+            .map(|(path, sha)| ResponseFile {
+                path: path.to_string(),
+                mode: "100644".to_string(),
+                _type: "blob".to_string(),
+                sha: sha.to_string(),
+                size: None,
+                url: String::new(),
+            })
+            .collect(),
+    }
+}
+
+// The files of a source by their path relative to the source directory
+type SourceFiles<'a> = &'a [(&'a str, &'a [u8])];
+
+// The base64 encoded files of the updated sources, as requested from the extension repository
+pub fn updated_extensions(sources: &[(&str, SourceFiles)]) -> UpdatedExtensions {
+    sources
+        .iter()
+        .map(|(id, files)| {
+            (
+                id.to_string(),
+                files
+                    .iter()
+                    .map(|(path, content)| {
+                        (format!("{}/{}", id, path), BASE64_STANDARD.encode(content))
+                    })
+                    .collect::<HashMap<String, String>>(),
+            )
+        })
+        .collect()
+}
+
+pub fn change(
+    id: &str,
+    old_version: Option<&str>,
+    new_version: &str,
+    outcome: Outcome,
+) -> SourceChange {
+    SourceChange {
+        id: id.to_string(),
+        name: id.to_string(),
+        old_version: old_version.map(str::to_string),
+        new_version: new_version.to_string(),
+        outcome,
+        changed_fields: vec![],
+        capabilities: vec![],
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use base64::prelude::*;
+
+use crate::{
+    requests::GetBaseTreeResponse,
+    versioning::{
+        is_valid_color, Badges, Source, Versioning, DEFAULT_BADGE_BACKGROUND_COLOR,
+        DEFAULT_BADGE_TEXT_COLOR,
+    },
+};
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:0 auto;max-width:960px;padding:1rem;color:#222}\
+a{color:inherit}\
+.sources{list-style:none;padding:0;display:grid;grid-template-columns:repeat(auto-fill,minmax(280px,1fr));gap:1rem}\
+.source{border:1px solid #ddd;border-radius:8px;padding:1rem}\
+.icon{width:48px;height:48px;border-radius:8px;vertical-align:middle;margin-right:.5rem}\
+.badge{display:inline-block;border-radius:4px;padding:0 .4rem;margin:.2rem .2rem 0 0;font-size:.8rem}\
+.meta{color:#666;font-size:.9rem}";
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// The registry entries are normalized when publishing, but entries of earlier
+// runs may still contain colors which can not be displayed
+fn color<'a>(value: &'a str, fallback: &'static str) -> &'a str {
+    match is_valid_color(value) {
+        true => value,
+        false => fallback,
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+fn badges<'a>(badges: impl Iterator<Item = &'a Badges>) -> String {
+    badges
+        .map(|badge| {
+            format!(
+                "<span class=\"badge\" style=\"color:{};background-color:{}\">{}</span>",
+                color(badge.text_color(), DEFAULT_BADGE_TEXT_COLOR),
+                color(badge.background_color(), DEFAULT_BADGE_BACKGROUND_COLOR),
+                escape(badge.label())
+            )
+        })
+        .collect()
+}
+
+fn source_page(source: &Source) -> String {
+    let mut body = format!(
+        "<p><a href=\"../index.html\">All sources</a></p>\n<h1><img class=\"icon\" src=\"../{}/static/{}\" alt=\"\">{}</h1>\n<p>{}</p>\n<p>{}</p>\n<ul class=\"meta\">\n<li>Version: {}</li>\n<li>Content rating: {}</li>\n",
+        escape(source.id()),
+        escape(source.icon()),
+        escape(source.name()),
+        badges(source.badges()),
+        escape(source.description()),
+        escape(source.version()),
+        escape(source.content_rating().as_str())
+    );
+
+    if let Some(language) = source.language() {
+        body.push_str(&format!("<li>Language: {}</li>\n", escape(language)));
+    }
+
+    let capabilities = source.capabilities();
+
+    if !capabilities.is_empty() {
+        body.push_str(&format!(
+            "<li>Capabilities: {}</li>\n",
+            escape(&capabilities.join(", "))
+        ));
+    }
+
+    if let Some(build) = source.build() {
+        body.push_str(&format!(
+            "<li>Repository: <a href=\"https://github.com/{}\">{}</a></li>\n",
+            escape(build.repository()),
+            escape(build.repository())
+        ));
+    }
+
+    body.push_str("</ul>\n");
+
+    let developers: Vec<String> = source
+        .developers()
+        .map(|developer| {
+            let link = developer.website().map(str::to_string).or_else(|| {
+                developer
+                    .github()
+                    .map(|github| format!("https://github.com/{}", github))
+            });
+
+            match link {
+                Some(link) if link.starts_with("https://") || link.starts_with("http://") => {
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>",
+                        escape(&link),
+                        escape(developer.name())
+                    )
+                }
+                _ => format!("<li>{}</li>", escape(developer.name())),
+            }
+        })
+        .collect();
+
+    if !developers.is_empty() {
+        body.push_str(&format!(
+            "<h2>Developers</h2>\n<ul>\n{}\n</ul>\n",
+            developers.join("\n")
+        ));
+    }
+
+    page(source.name(), &body)
+}
+
+fn index_page(versioning: &Versioning) -> String {
+    let mut sources: Vec<&Source> = versioning.sources().iter().collect();
+    sources.sort_by_key(|source| source.name().to_lowercase());
+
+    let items: String = sources
+        .iter()
+        .map(|source| {
+            format!(
+                "<li class=\"source\">\n<a href=\"sources/{}.html\"><img class=\"icon\" src=\"{}/static/{}\" alt=\"\"><strong>{}</strong></a>\n<div>{}</div>\n<p>{}</p>\n<div class=\"meta\">{} · {}</div>\n</li>\n",
+                escape(source.id()),
+                escape(source.id()),
+                escape(source.icon()),
+                escape(source.name()),
+                badges(source.badges()),
+                escape(source.description()),
+                escape(source.version()),
+                escape(source.content_rating().as_str())
+            )
+        })
+        .collect();
+
+    let body = format!(
+        "<h1>{}</h1>\n<p>{}</p>\n<p class=\"meta\">{} sources</p>\n<ul class=\"sources\">\n{}</ul>\n",
+        escape(versioning.repository().name()),
+        escape(versioning.repository().description()),
+        sources.len(),
+        items
+    );
+
+    page(versioning.repository().name(), &body)
+}
+
+// Renders the browse pages of the merged registry, the pages are
+// keyed by their path in the registry and base64 encoded.
+pub fn render(versioning: &Versioning) -> HashMap<String, String> {
+    let mut pages = HashMap::new();
+
+    pages.insert(
+        "index.html".to_string(),
+        BASE64_STANDARD.encode(index_page(versioning)),
+    );
+
+    for source in versioning.sources() {
+        pages.insert(
+            format!("sources/{}.html", source.id()),
+            BASE64_STANDARD.encode(source_page(source)),
+        );
+    }
+
+    println!("Rendered the browse pages of the registry");
+
+    pages
+}
+
+// The pages of sources which are no longer listed, e.g. adult sources which the
+// registry policy keeps separate, are removed from the registry
+pub fn stale_pages(
+    registry_tree: &GetBaseTreeResponse,
+    pages: &HashMap<String, String>,
+) -> Vec<String> {
+    registry_tree
+        .tree
+        .iter()
+        .filter(|file| {
+            file._type == "blob"
+                && file.path.starts_with("sources/")
+                && file.path.ends_with(".html")
+                && !pages.contains_key(&file.path)
+        })
+        .map(|file| file.path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{source, tree, versioning};
+
+    fn decoded(pages: &HashMap<String, String>, path: &str) -> String {
+        String::from_utf8(BASE64_STANDARD.decode(&pages[path]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn escape_replaces_the_html_special_characters() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn every_listed_source_has_an_escaped_page() {
+        let mut unsafe_source = source("B", "1.0.0");
+        unsafe_source["name"] = json!("<script>alert(1)</script>");
+
+        let pages = render(&versioning(vec![source("A", "1.0.0"), unsafe_source]));
+
+        let mut paths: Vec<&String> = pages.keys().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["index.html", "sources/A.html", "sources/B.html"]
+        );
+
+        let page = decoded(&pages, "sources/B.html");
+        assert!(page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!page.contains("<script>"));
+        assert!(!decoded(&pages, "index.html").contains("<script>"));
+    }
+
+    #[test]
+    fn pages_of_unlisted_sources_are_stale() {
+        let pages = render(&versioning(vec![source("A", "1.0.0")]));
+        let registry_tree = tree(&[
+            ("index.html", "a"),
+            ("sources/A.html", "b"),
+            ("sources/Adult.html", "c"),
+            ("sources/notes.txt", "d"),
+            ("Adult/index.js", "e"),
+        ]);
+
+        assert_eq!(
+            stale_pages(&registry_tree, &pages),
+            vec!["sources/Adult.html"]
+        );
+    }
+}
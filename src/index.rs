@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use base64::prelude::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::versioning::{Source, Versioning};
+
+// An index of the registry grouped by the repository every source was published
+// from, the ownership is taken from the build metadata recorded per source.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryIndex {
+    generated_at: String,
+    source_count: usize,
+    repository_count: usize,
+    repositories: Vec<IndexRepository>,
+    // Sources which were published before build metadata was recorded
+    unattributed_sources: Vec<IndexSource>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexRepository {
+    repository: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_updated: Option<String>,
+    source_count: usize,
+    sources: Vec<IndexSource>,
+}
+
+// The registry entry of a source with the names of its capabilities
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSource {
+    #[serde(flatten)]
+    source: Source,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    capability_names: Vec<String>,
+}
+
+// Only the repository metadata is read from the previous index, so a changed
+// or hand edited source entry never makes the previous index unreadable
+#[derive(Debug, Deserialize)]
+pub struct PreviousIndex {
+    #[serde(default)]
+    repositories: Vec<PreviousRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PreviousRepository {
+    repository: String,
+    name: Option<String>,
+    description: Option<String>,
+    last_updated: Option<String>,
+}
+
+impl IndexSource {
+    fn new(source: &Source) -> IndexSource {
+        IndexSource {
+            source: source.clone(),
+            capability_names: source
+                .capabilities()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+// The repository metadata is only known for the publishing repository, the metadata of
+// the other repositories is kept from the previous index. Without a publishing repository,
+// e.g. when rolling back, the metadata of every repository is kept.
+pub fn generate(
+    registry_versioning: &Versioning,
+    previous_index: Option<PreviousIndex>,
+    publisher: Option<(&str, &Versioning)>,
+) -> RegistryIndex {
+    let now = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+
+    let mut previous_repositories: BTreeMap<String, PreviousRepository> = previous_index
+        .map(|index| index.repositories)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|index_repository| (index_repository.repository.clone(), index_repository))
+        .collect();
+
+    let mut grouped_sources: BTreeMap<String, Vec<&Source>> = BTreeMap::new();
+    let mut unattributed_sources = vec![];
+
+    for source in registry_versioning.sources() {
+        match source.build() {
+            Some(build) => grouped_sources
+                .entry(build.repository().to_string())
+                .or_default()
+                .push(source),
+            None => unattributed_sources.push(IndexSource::new(source)),
+        }
+    }
+
+    let repositories: Vec<IndexRepository> = grouped_sources
+        .into_iter()
+        .map(|(source_repository, mut sources)| {
+            sources.sort_by(|a, b| a.id().cmp(b.id()));
+
+            let previous = previous_repositories.remove(&source_repository);
+
+            let (name, description, last_updated) = match publisher {
+                Some((repository, repository_versioning)) if source_repository == repository => (
+                    Some(repository_versioning.repository().name().to_string()),
+                    Some(repository_versioning.repository().description().to_string()),
+                    Some(now.clone()),
+                ),
+                _ => match previous {
+                    Some(previous) => (previous.name, previous.description, previous.last_updated),
+                    None => (
+                        None,
+                        None,
+                        sources
+                            .iter()
+                            .filter_map(|source| source.build())
+                            .map(|build| build.build_time().to_string())
+                            .max(),
+                    ),
+                },
+            };
+
+            IndexRepository {
+                repository: source_repository,
+                name,
+                description,
+                last_updated,
+                source_count: sources.len(),
+                sources: sources.into_iter().map(IndexSource::new).collect(),
+            }
+        })
+        .collect();
+
+    println!("Generated the registry index");
+
+    RegistryIndex {
+        generated_at: now,
+        source_count: registry_versioning.sources().len(),
+        repository_count: repositories.len(),
+        repositories,
+        unattributed_sources,
+    }
+}
+
+impl RegistryIndex {
+    pub fn to_base64(&self) -> Result<String, u8> {
+        match serde_json::to_vec_pretty(&self) {
+            Ok(mut buffer) => {
+                buffer.push(b'\n');
+
+                println!("Encoded the index file to base64");
+
+                Ok(BASE64_STANDARD.encode(&buffer))
+            }
+            Err(err) => {
+                eprintln!(
+                    "An error occurred while serializing the index struct: {}",
+                    &err
+                );
+                Err(0x1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{source, versioning};
+
+    fn built_source(id: &str, repository: &str, build_time: &str) -> serde_json::Value {
+        let mut built_source = source(id, "1.0.0");
+        built_source["build"] = json!({
+            "toolchain": "0.9.0",
+            "types": "0.9.0",
+            "buildTime": build_time,
+            "repository": repository
+        });
+        built_source
+    }
+
+    fn previous_index() -> PreviousIndex {
+        serde_json::from_value(json!({
+            "repositories": [{
+                "repository": "paperback-community/other",
+                "name": "Other",
+                "description": "Another repository",
+                "lastUpdated": "2024-02-01T00:00:00.000Z",
+                "sources": [{ "a changed": "source entry" }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sources_are_grouped_by_the_repository_they_were_published_from() {
+        let registry_versioning = versioning(vec![
+            built_source("B", "paperback-community/extensions", "2024-01-01"),
+            built_source("A", "paperback-community/extensions", "2024-01-02"),
+            built_source("C", "paperback-community/third", "2024-01-03"),
+            source("D", "1.0.0"),
+        ]);
+
+        let index = generate(&registry_versioning, None, None);
+
+        assert_eq!(index.source_count, 4);
+        assert_eq!(index.repository_count, 2);
+        assert_eq!(
+            index.repositories[0].repository,
+            "paperback-community/extensions"
+        );
+        assert_eq!(
+            index.repositories[0]
+                .sources
+                .iter()
+                .map(|index_source| index_source.source.id())
+                .collect::<Vec<&str>>(),
+            vec!["A", "B"]
+        );
+        // Without previous metadata the newest build time is used
+        assert_eq!(
+            index.repositories[1].last_updated.as_deref(),
+            Some("2024-01-03")
+        );
+        assert_eq!(index.unattributed_sources.len(), 1);
+        assert_eq!(
+            index.repositories[0].sources[0].capability_names,
+            vec!["Manga chapters"]
+        );
+    }
+
+    #[test]
+    fn the_metadata_of_the_publisher_is_updated_and_of_others_kept() {
+        let registry_versioning = versioning(vec![
+            built_source("A", "paperback-community/extensions", "2024-01-01"),
+            built_source("B", "paperback-community/other", "2024-01-01"),
+        ]);
+        let repository_versioning = versioning(vec![]);
+
+        let index = generate(
+            &registry_versioning,
+            Some(previous_index()),
+            Some(("paperback-community/extensions", &repository_versioning)),
+        );
+
+        assert_eq!(index.repositories[0].name.as_deref(), Some("Example"));
+        assert_eq!(
+            index.repositories[0].last_updated,
+            Some(index.generated_at.clone())
+        );
+        assert_eq!(index.repositories[1].name.as_deref(), Some("Other"));
+        assert_eq!(
+            index.repositories[1].last_updated.as_deref(),
+            Some("2024-02-01T00:00:00.000Z")
+        );
+
+        // Without a publisher the metadata of every repository is kept
+        let index = generate(&registry_versioning, Some(previous_index()), None);
+
+        assert_eq!(index.repositories[0].name, None);
+        assert_eq!(index.repositories[1].name.as_deref(), Some("Other"));
+    }
+}
@@ -1,9 +1,39 @@
 use std::{collections::HashMap, env, process::ExitCode};
 
+mod archive;
+mod changelog;
+mod channel;
+mod commit_message;
+mod content;
+#[cfg(test)]
+mod fixtures;
+mod html;
+mod index;
+mod manifest;
+mod promote;
+mod pull_request;
+mod registry_policy;
 mod requests;
+mod rollback;
+mod target;
 mod utils;
+mod validation;
 mod versioning;
 
+const REGISTRY_REPOSITORY: &str = "paperback-community/extensions-test";
+const REGISTRY_BRANCH: &str = "master";
+
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum PublishMode {
+    Commit,
+    PullRequest,
+}
+
+// Other runs may update the registry at the same time, when the registry
+// branch moved while updating it the update is retried on the new state.
+const MAX_UPDATE_ATTEMPTS: u8 = 3;
+
 fn main() -> ExitCode {
     #[cfg(feature = "dotenv")]
     if let Err(exit_code) = utils::env::load_dotenv() {
@@ -11,191 +41,902 @@ fn main() -> ExitCode {
         return ExitCode::from(exit_code);
     }
 
-    if let Err(exit_code) = utils::env::validate() {
-        eprintln!("Exiting the program");
-        return ExitCode::from(exit_code);
-    }
+    let args = match utils::args::parse() {
+        Ok(args) => args,
+        Err(exit_code) => {
+            eprintln!("Exiting the program");
+            return ExitCode::from(exit_code);
+        }
+    };
 
-    let request_client;
-    match requests::new() {
-        Ok(r_request_client) => request_client = r_request_client,
+    let config = match utils::config::load(&args) {
+        Ok(config) => config,
         Err(exit_code) => {
             eprintln!("Exiting the program");
             return ExitCode::from(exit_code);
         }
+    };
+
+    if let utils::args::Command::ConfigShow = args.command {
+        config.show();
+        return ExitCode::from(0x0);
     }
 
-    let mut registry_versioning;
-    match request_client.get_files(
-        &"paperback-community/extensions".to_string(),
-        &"versioning.json".to_string(),
-        &"master".to_string(),
-    ) {
-        Ok(requests::GetContent::Struct(response)) => {
-            match versioning::parse_versioning(&response.content) {
-                Ok(versioning) => {
-                    registry_versioning = versioning;
-                }
-                Err(exitcode) => {
-                    eprintln!("Exiting the program");
-                    return ExitCode::from(exitcode);
-                }
+    // Verifying a registry checkout is done locally, without any requests
+    if let utils::args::Command::Verify { path } = &args.command {
+        return match manifest::verify(path, config.public_key.as_ref()) {
+            Ok(()) => ExitCode::from(0x0),
+            Err(exit_code) => {
+                eprintln!("Exiting the program");
+                ExitCode::from(exit_code)
             }
+        };
+    }
+
+    let request_client = match requests::new(config.pat.as_deref().unwrap_or_default()) {
+        Ok(request_client) => request_client,
+        Err(exit_code) => {
+            eprintln!("Exiting the program");
+            return ExitCode::from(exit_code);
         }
-        Ok(requests::GetContent::List(_)) => {
-            panic!("this API request should return a single file")
-        }
-        Err(exitcode) => {
+    };
+
+    let targets = match target::select(&config.targets, args.target.as_deref()) {
+        Ok(targets) => targets,
+        Err(exit_code) => {
             eprintln!("Exiting the program");
-            return ExitCode::from(exitcode);
+            return ExitCode::from(exit_code);
         }
+    };
+
+    // Rolling back and promoting changes a single registry
+    if !matches!(args.command, utils::args::Command::Publish) && targets.len() > 1 {
+        eprintln!("Multiple registry targets are configured, select the registry to change with --target <repository>");
+        eprintln!("Exiting the program");
+        return ExitCode::from(0x1);
     }
 
-    let repository_versioning;
-    match request_client.get_files(
-        &env::var("REGISTRY_MANAGER_REPOSITORY").unwrap(),
-        &"versioning.json".to_string(),
-        &"gh-pages".to_string(),
-    ) {
-        Ok(requests::GetContent::Struct(response)) => {
-            match versioning::parse_versioning(&response.content) {
-                Ok(versioning) => {
-                    repository_versioning = versioning;
+    match args.command {
+        utils::args::Command::Publish => {
+            run_publish(&request_client, &config, args.allow_downgrade, &targets)
+        }
+        utils::args::Command::Rollback { id, to, channel } => {
+            match rollback::run(
+                &request_client,
+                &config,
+                targets[0],
+                &id,
+                to.as_deref(),
+                channel,
+            ) {
+                Ok(()) => ExitCode::from(0x0),
+                Err(exit_code) => {
+                    eprintln!("Exiting the program");
+                    ExitCode::from(exit_code)
                 }
-                Err(exitcode) => {
+            }
+        }
+        utils::args::Command::Promote { id, from, to } => {
+            match promote::run(&request_client, &config, targets[0], &id, from, to) {
+                Ok(()) => ExitCode::from(0x0),
+                Err(exit_code) => {
                     eprintln!("Exiting the program");
-                    return ExitCode::from(exitcode);
+                    ExitCode::from(exit_code)
                 }
             }
         }
-        Ok(requests::GetContent::List(_)) => {
-            panic!("this API request should return a single file")
+        utils::args::Command::Verify { .. } | utils::args::Command::ConfigShow => {
+            unreachable!("these commands are handled before this")
         }
-        Err(exitcode) => {
+    }
+}
+
+fn run_publish(
+    request_client: &requests::Requests,
+    config: &utils::config::Config,
+    allow_downgrade: Vec<String>,
+    targets: &[&target::Target],
+) -> ExitCode {
+    // Every file of the extension repository is read from the same commit,
+    // even when the gh-pages branch is updated while the program is running
+    let repository_commit_sha = match request_client.get_ref(config.repository(), "gh-pages") {
+        Ok(repository_ref) => repository_ref.object.sha,
+        Err(exit_code) => {
+            eprintln!("Exiting the program");
+            return ExitCode::from(exit_code);
+        }
+    };
+
+    let mut repository_versioning = match get_versioning(
+        request_client,
+        config.repository(),
+        "versioning.json",
+        &repository_commit_sha,
+    ) {
+        Ok(repository_versioning) => repository_versioning,
+        Err(exit_code) => {
             eprintln!("Exiting the program");
-            return ExitCode::from(exitcode);
+            return ExitCode::from(exit_code);
+        }
+    };
+
+    repository_versioning.normalize();
+
+    let repository_tree =
+        match request_client.get_tree(config.repository(), &repository_commit_sha, true) {
+            Ok(repository_tree) => repository_tree,
+            Err(exit_code) => {
+                eprintln!("Exiting the program");
+                return ExitCode::from(exit_code);
+            }
+        };
+
+    let channel = config.channel;
+
+    println!(
+        "Publishing to the {} channel of the registry",
+        channel.name()
+    );
+
+    let source_build = versioning::SourceBuild::new(
+        &repository_versioning,
+        config.repository().to_string(),
+        env::var("GITHUB_SHA").ok(),
+    );
+
+    // The files of the extensions are requested once and reused between attempts and targets
+    let mut extension_files = HashMap::new();
+    let mut results = vec![];
+
+    for target in targets.iter() {
+        println!("Publishing to the registry target {}", target.name());
+
+        let policy = versioning::Policy {
+            allow_downgrade: allow_downgrade.clone(),
+            metadata: target.metadata_policy.unwrap_or(config.metadata_policy),
+            content: target.content_policy.unwrap_or(config.content_policy),
+            types: versioning::TypesPolicy::new(
+                &config.types_ranges,
+                config.types_prerelease,
+                &target.branch,
+            ),
+            channel,
+        };
+
+        let result = publish_target(
+            request_client,
+            config,
+            target,
+            &repository_versioning,
+            &repository_tree,
+            &source_build,
+            &policy,
+            &mut extension_files,
+        );
+
+        results.push((target.name(), result));
+    }
+
+    // A failing target does not stop the other targets, but the run fails
+    println!("Results of the registry targets:");
+
+    for (name, result) in results.iter() {
+        match result {
+            Ok(()) => println!("{}: Published", name),
+            Err(_) => println!("{}: Failed", name),
         }
     }
 
-    let mut updated_extensions;
-    match versioning::update_registry_versioning(&mut registry_versioning, &repository_versioning) {
-        Ok(r_updated_extensions) => updated_extensions = r_updated_extensions,
-        Err(exitcode) => {
+    // TODO:
+    // - Switch to specific exit codes
+    // - Switch from std prints to tracing
+
+    match results.into_iter().find_map(|(_, result)| result.err()) {
+        Some(exit_code) => {
             eprintln!("Exiting the program");
-            return ExitCode::from(exitcode);
+            ExitCode::from(exit_code)
         }
+        None => ExitCode::from(0x0),
     }
+}
 
-    for updated_extension in updated_extensions.iter_mut() {
+// Publishes to a single target, the run is retried when the registry branch moved
+#[allow(clippy::too_many_arguments)]
+fn publish_target(
+    request_client: &requests::Requests,
+    config: &utils::config::Config,
+    target: &target::Target,
+    repository_versioning: &versioning::Versioning,
+    repository_tree: &requests::GetBaseTreeResponse,
+    source_build: &versioning::SourceBuild,
+    policy: &versioning::Policy,
+    extension_files: &mut HashMap<String, HashMap<String, String>>,
+) -> Result<(), u8> {
+    retry_when_moved(|| {
+        publish(
+            request_client,
+            config,
+            target,
+            repository_versioning,
+            repository_tree,
+            source_build,
+            policy,
+            extension_files,
+        )
+    })?;
+
+    println!("Published the updated extensions to the registry");
+
+    Ok(())
+}
+
+// Runs an update of the registry until its ref update is not rejected because
+// another run moved the registry branch in the meantime
+fn retry_when_moved(mut update: impl FnMut() -> Result<requests::RefUpdate, u8>) -> Result<(), u8> {
+    for attempt in 1..=MAX_UPDATE_ATTEMPTS {
         println!(
-            "Requesting the updated files and creating blobs for {}",
-            updated_extension.0
+            "Updating the registry, attempt {} of {}",
+            attempt, MAX_UPDATE_ATTEMPTS
         );
 
-        match request_client.get_files(
-            &env::var("REGISTRY_MANAGER_REPOSITORY").unwrap(),
-            &format!("{}/index.js", &updated_extension.0),
-            &"gh-pages".to_string(),
-        ) {
-            Ok(requests::GetContent::List(_)) => {
-                panic!("this API request should return a single file")
-            }
-            Ok(requests::GetContent::Struct(response)) => {
-                updated_extension.1.insert(response.path, response.content);
+        match update()? {
+            requests::RefUpdate::Updated => return Ok(()),
+            requests::RefUpdate::Moved => {
+                eprintln!("The registry was updated by another run in the meantime, retrying on the new state");
             }
-            Err(exit_code) => {
-                eprintln!("Exiting the program");
-                return ExitCode::from(exit_code);
+        }
+    }
+
+    eprintln!(
+        "The registry kept being updated by other runs, gave up after {} attempts",
+        MAX_UPDATE_ATTEMPTS
+    );
+
+    Err(0x1)
+}
+
+// Reads the versioning file and the tree of the registry from its current commit, merges the repository versioning file
+// into it and commits the result with that commit as the parent. The ref update
+// is not forced, so a commit by another run in the meantime is never overwritten.
+#[allow(clippy::too_many_arguments)]
+fn publish(
+    request_client: &requests::Requests,
+    config: &utils::config::Config,
+    target: &target::Target,
+    repository_versioning: &versioning::Versioning,
+    repository_tree: &requests::GetBaseTreeResponse,
+    source_build: &versioning::SourceBuild,
+    policy: &versioning::Policy,
+    extension_files: &mut HashMap<String, HashMap<String, String>>,
+) -> Result<requests::RefUpdate, u8> {
+    let registry_ref = request_client.get_ref(&target.repository, &target.branch)?;
+    let registry_commit =
+        request_client.get_commit(&target.repository, &registry_ref.object.sha)?;
+
+    println!(
+        "Reading the registry at the following commit: {}",
+        &registry_commit.sha
+    );
+
+    let registry_tree =
+        request_client.get_tree(&target.repository, &registry_commit.tree.sha, true)?;
+
+    // The files of the channel, with their paths relative to the channel directory
+    let registry_base_tree = target.tree(policy.channel, &registry_tree);
+
+    let mut registry_versioning =
+        match get_channel_versioning(request_client, target, &registry_base_tree)? {
+            Some(registry_versioning) => registry_versioning,
+            None => {
+                println!(
+                    "The {} channel does not exist in the registry yet, creating it",
+                    policy.channel.name()
+                );
+                repository_versioning.without_sources()
             }
+        };
+
+    // Entries published before the normalization would otherwise differ
+    // from the normalized repository entries by their badges and developers
+    registry_versioning.normalize();
+
+    let registry_policy = get_registry_policy(request_client, target, &registry_tree)?;
+
+    let changed_content = content::changed_sources(repository_tree, &registry_base_tree);
+
+    let (mut updated_extensions, changes) = versioning::update_registry_versioning(
+        &mut registry_versioning,
+        repository_versioning,
+        source_build,
+        &changed_content,
+        policy,
+        &registry_policy,
+    )?;
+
+    versioning::print_summary(&changes);
+
+    for updated_extension in updated_extensions.iter_mut() {
+        if !extension_files.contains_key(&updated_extension.0) {
+            let files = get_extension_files(
+                request_client,
+                config.repository(),
+                &updated_extension.0,
+                repository_tree,
+            )?;
+            extension_files.insert(updated_extension.0.clone(), files);
         }
 
-        match request_client.get_files(
-            &env::var("REGISTRY_MANAGER_REPOSITORY").unwrap(),
-            &format!("{}/static", &updated_extension.0),
-            &"gh-pages".to_string(),
-        ) {
-            Ok(requests::GetContent::List(response)) => {
-                for file in response {
-                    if file._type != "file" {
-                        continue;
-                    }
-
-                    match request_client.get_files(
-                        &env::var("REGISTRY_MANAGER_REPOSITORY").unwrap(),
-                        &file.path,
-                        &"gh-pages".to_string(),
-                    ) {
-                        Ok(requests::GetContent::List(_)) => {
-                            panic!("this API request should return a single file")
-                        }
-                        Ok(requests::GetContent::Struct(response)) => {
-                            updated_extension.1.insert(response.path, response.content);
-                        }
-                        Err(exit_code) => {
-                            eprintln!("Exiting the program");
-                            return ExitCode::from(exit_code);
-                        }
-                    }
-                }
+        updated_extension.1 = extension_files[&updated_extension.0].clone();
+    }
+
+    validation::validate(
+        &updated_extensions,
+        &registry_versioning,
+        &registry_policy,
+        &config.limits,
+    )?;
+
+    let mut removed_files = vec![];
+
+    for (id, files) in updated_extensions.iter() {
+        removed_files.extend(content::removed_files(&registry_base_tree, id, files));
+    }
+
+    // The generated files of the registry are kept apart from the files of the
+    // sources, so they can never be mistaken for a source
+    let mut generated_files = HashMap::new();
+
+    if config.manifest {
+        let mut registry_manifest = match get_registry_file(
+            request_client,
+            target,
+            &registry_base_tree,
+            "manifest.json",
+        )? {
+            Some(content) => utils::json::parse_base64_json(&content, "manifest")?,
+            None => manifest::Manifest::default(),
+        };
+
+        for (id, files) in updated_extensions.iter() {
+            if let Some(source) = registry_versioning.source(id) {
+                registry_manifest.record(id, source.version(), files)?;
             }
-            Ok(requests::GetContent::Struct(_)) => {
-                panic!("this API request should return a list of files")
+        }
+
+        let (manifest_base64, signature_base64) =
+            registry_manifest.to_base64(config.signing_key.as_ref())?;
+
+        generated_files.insert("manifest.json".to_string(), manifest_base64);
+
+        match signature_base64 {
+            Some(signature_base64) => {
+                generated_files.insert("manifest.json.sig".to_string(), signature_base64);
             }
-            Err(exit_code) => {
-                eprintln!("Exiting the program");
-                return ExitCode::from(exit_code);
+            // A signature of an earlier manifest would no longer match
+            None => {
+                if registry_base_tree
+                    .tree
+                    .iter()
+                    .any(|file| file.path == "manifest.json.sig")
+                {
+                    removed_files.push("manifest.json.sig".to_string());
+                }
             }
         }
     }
 
-    match registry_versioning.to_base64() {
-        Ok(registry_versioning_base64) => {
-            let mut versioning_hashmap = HashMap::new();
-            versioning_hashmap.insert("versioning.json".to_string(), registry_versioning_base64);
+    if let Some(retention) = config.archive_retention {
+        let (archived_files, archive_removed_files) = archive::update(
+            &mut registry_versioning,
+            &registry_base_tree,
+            &updated_extensions,
+            retention,
+        );
+
+        removed_files.extend(archive_removed_files);
+        generated_files.extend(archived_files);
+    }
+
+    let (versioning_files, removed_versioning_files) = channel_versioning_files(
+        &registry_versioning,
+        &registry_policy,
+        &registry_base_tree,
+        &config.output_format,
+    )?;
+
+    removed_files.extend(removed_versioning_files);
+    generated_files.extend(versioning_files);
+
+    let (derived_files, removed_derived_files) = derived_files(
+        request_client,
+        config,
+        target,
+        &registry_base_tree,
+        &listed_versioning(&registry_versioning, &registry_policy),
+        config.repository(),
+        &changes,
+        Some(repository_versioning),
+    )?;
+
+    removed_files.extend(removed_derived_files);
+    generated_files.extend(derived_files);
+
+    // The paths of the files are relative to the channel directory up to here
+    let files = updated_extensions
+        .into_iter()
+        .flat_map(|(_, files)| files)
+        .chain(generated_files)
+        .map(|(path, content)| (target.path(policy.channel, &path), content))
+        .collect();
+    let removed_files: Vec<String> = removed_files
+        .iter()
+        .map(|path| target.path(policy.channel, path))
+        .collect();
+
+    let registry_update_tree = request_client.create_tree(
+        &target.repository,
+        registry_tree.sha,
+        files,
+        removed_files.clone(),
+    )?;
+
+    commit_to_registry(
+        request_client,
+        config,
+        target,
+        policy.channel,
+        registry_update_tree.sha,
+        registry_commit.sha,
+        commit_message::generate(
+            config.repository(),
+            policy.channel,
+            &changes,
+            config.commit_template.as_deref(),
+        ),
+        pull_request::publish_proposal(
+            config.repository(),
+            policy.channel,
+            &changes,
+            &removed_files,
+        ),
+    )
+}
+
+// Commits the tree on top of the registry commit, depending on the publish mode of the
+// target the registry branch is updated or the commit is proposed as a pull request
+#[allow(clippy::too_many_arguments)]
+fn commit_to_registry(
+    request_client: &requests::Requests,
+    config: &utils::config::Config,
+    target: &target::Target,
+    channel: channel::Channel,
+    tree_sha: String,
+    parent_sha: String,
+    message: String,
+    proposal: pull_request::Proposal,
+) -> Result<requests::RefUpdate, u8> {
+    let commit = request_client.create_commit(&target.repository, message, tree_sha, parent_sha)?;
+
+    match target.publish_mode.unwrap_or(config.publish_mode) {
+        PublishMode::Commit => {
+            request_client.update_ref(&target.repository, &target.branch, commit.sha, false)
+        }
+        // The pull request is merged by the registry maintainers,
+        // so the registry branch itself is never updated here
+        PublishMode::PullRequest => {
+            pull_request::open(request_client, target, channel, proposal, commit.sha)?;
+
+            Ok(requests::RefUpdate::Updated)
+        }
+    }
+}
+
+// Generates the changelog, the index and the browse pages of a channel, which are derived from
+// its listed sources and regenerated whenever they change. The repository is recorded in the
+// changelog, the metadata of the repository versioning file is written to the index. Returns
+// the encoded files and the paths of the files to remove, relative to the channel directory.
+#[allow(clippy::too_many_arguments)]
+fn derived_files(
+    request_client: &requests::Requests,
+    config: &utils::config::Config,
+    target: &target::Target,
+    registry_tree: &requests::GetBaseTreeResponse,
+    listed_versioning: &versioning::Versioning,
+    repository: &str,
+    changes: &[versioning::SourceChange],
+    repository_versioning: Option<&versioning::Versioning>,
+) -> Result<(HashMap<String, String>, Vec<String>), u8> {
+    let mut files = HashMap::new();
+    let mut removed_files = vec![];
+
+    if !matches!(config.changelog, changelog::ChangelogFormat::None) {
+        let mut registry_changelog =
+            match get_registry_file(request_client, target, registry_tree, "changelog.json")? {
+                Some(content) => utils::json::parse_base64_json(&content, "changelog")?,
+                None => changelog::Changelog::default(),
+            };
+
+        registry_changelog.record(repository, changes);
 
-            updated_extensions.push(("Versioning".to_string(), versioning_hashmap))
+        files.insert(
+            "changelog.json".to_string(),
+            registry_changelog.to_base64()?,
+        );
+
+        if let changelog::ChangelogFormat::Markdown = config.changelog {
+            files.insert(
+                "CHANGELOG.md".to_string(),
+                registry_changelog.to_markdown_base64(),
+            );
         }
-        Err(exit_code) => {
-            eprintln!("Exiting the program");
-            return ExitCode::from(exit_code);
+    }
+
+    if config.index {
+        let previous_index =
+            match get_registry_file(request_client, target, registry_tree, "index.json")? {
+                Some(content) => Some(utils::json::parse_base64_json(&content, "index")?),
+                None => None,
+            };
+
+        let registry_index = index::generate(
+            listed_versioning,
+            previous_index,
+            repository_versioning.map(|repository_versioning| (repository, repository_versioning)),
+        );
+
+        files.insert("index.json".to_string(), registry_index.to_base64()?);
+    }
+
+    if config.html {
+        let pages = html::render(listed_versioning);
+
+        removed_files.extend(html::stale_pages(registry_tree, &pages));
+        files.extend(pages);
+    }
+
+    Ok((files, removed_files))
+}
+
+fn get_versioning(
+    request_client: &requests::Requests,
+    repository: &str,
+    path: &str,
+    sha_ref: &str,
+) -> Result<versioning::Versioning, u8> {
+    match request_client.get_files(repository, path, sha_ref)? {
+        requests::GetContent::Struct(response) => {
+            utils::json::parse_base64_json(&response.content, "versioning")
+        }
+        requests::GetContent::List(_) => {
+            panic!("this API request should return a single file")
         }
     }
+}
 
-    let registry_base_tree_sha;
-    match request_client.get_tree(
-        &"paperback-community/extensions-test".to_string(),
-        &"master".to_string(),
-    ) {
-        Ok(r_registry_base_tree) => registry_base_tree_sha = r_registry_base_tree.sha,
-        Err(exit_code) => {
-            eprintln!("Exiting the program");
-            return ExitCode::from(exit_code);
+// Reads the policy of the registry maintainers, which applies to every channel
+fn get_registry_policy(
+    request_client: &requests::Requests,
+    target: &target::Target,
+    registry_tree: &requests::GetBaseTreeResponse,
+) -> Result<registry_policy::RegistryPolicy, u8> {
+    match get_registry_file(
+        request_client,
+        target,
+        &target.tree(channel::Channel::Stable, registry_tree),
+        registry_policy::PATH,
+    )? {
+        Some(content) => utils::json::parse_base64_json(&content, "registry policy"),
+        None => Ok(registry_policy::RegistryPolicy::default()),
+    }
+}
+
+// Reads the versioning file of a channel, the adult sources of a separate
+// versioning file are merged into it
+fn get_channel_versioning(
+    request_client: &requests::Requests,
+    target: &target::Target,
+    registry_tree: &requests::GetBaseTreeResponse,
+) -> Result<Option<versioning::Versioning>, u8> {
+    let Some(content) =
+        get_registry_file(request_client, target, registry_tree, "versioning.json")?
+    else {
+        return Ok(None);
+    };
+
+    let mut registry_versioning: versioning::Versioning =
+        utils::json::parse_base64_json(&content, "versioning")?;
+
+    if let Some(content) = get_registry_file(
+        request_client,
+        target,
+        registry_tree,
+        versioning::ADULT_VERSIONING_PATH,
+    )? {
+        registry_versioning.merge(utils::json::parse_base64_json(
+            &content,
+            "adult versioning",
+        )?);
+    }
+
+    Ok(Some(registry_versioning))
+}
+
+// The sources listed in the versioning file of a channel, without the adult
+// sources when the registry policy keeps them separate or rejects them
+fn listed_versioning(
+    registry_versioning: &versioning::Versioning,
+    registry_policy: &registry_policy::RegistryPolicy,
+) -> versioning::Versioning {
+    match registry_policy.adult_sources() {
+        registry_policy::AdultSources::Allow => registry_versioning.clone(),
+        _ => registry_versioning.split_adult().0,
+    }
+}
+
+// Encodes the versioning file of a channel, when the registry policy keeps the adult sources
+// separate they are written to their own versioning file. Returns the encoded files and the
+// paths of the files to remove, relative to the channel directory.
+fn channel_versioning_files(
+    registry_versioning: &versioning::Versioning,
+    registry_policy: &registry_policy::RegistryPolicy,
+    registry_tree: &requests::GetBaseTreeResponse,
+    output_format: &versioning::OutputFormat,
+) -> Result<(HashMap<String, String>, Vec<String>), u8> {
+    let adult_file_exists = registry_tree
+        .tree
+        .iter()
+        .any(|file| file.path == versioning::ADULT_VERSIONING_PATH);
+
+    let mut files = HashMap::new();
+    let mut removed_files = vec![];
+
+    match registry_policy.adult_sources() {
+        // The adult sources of an earlier policy are already merged into the versioning file
+        registry_policy::AdultSources::Allow => {
+            if adult_file_exists {
+                removed_files.push(versioning::ADULT_VERSIONING_PATH.to_string());
+            }
+        }
+        adult_sources => {
+            let (_, adult_versioning) = registry_versioning.split_adult();
+
+            // Adult sources which were published before the policy rejected them are
+            // never listed, but they are kept so the registry maintainers can remove them
+            if let registry_policy::AdultSources::Reject = adult_sources {
+                if !adult_versioning.sources().is_empty() {
+                    println!(
+                        "The registry policy rejects adult sources, the following adult sources are kept in {} instead of versioning.json: {}",
+                        versioning::ADULT_VERSIONING_PATH,
+                        adult_versioning
+                            .sources()
+                            .iter()
+                            .map(|source| source.id())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    );
+                }
+            }
+
+            if !adult_versioning.sources().is_empty() || adult_file_exists {
+                files.insert(
+                    versioning::ADULT_VERSIONING_PATH.to_string(),
+                    adult_versioning.to_base64(output_format)?,
+                );
+            }
         }
     }
 
-    let registry_update_tree;
-    match request_client.create_tree(registry_base_tree_sha, updated_extensions) {
-        Ok(r_registry_update_tree) => registry_update_tree = r_registry_update_tree,
-        Err(exit_code) => {
-            eprintln!("Exiting the program");
-            return ExitCode::from(exit_code);
+    files.insert(
+        "versioning.json".to_string(),
+        listed_versioning(registry_versioning, registry_policy).to_base64(output_format)?,
+    );
+
+    Ok((files, removed_files))
+}
+
+// Requests a file of a registry channel, when it exists in the tree of the channel.
+// The file is read as a blob of the tree, as the contents API does not return the
+// content of files larger than 1 MB.
+fn get_registry_file(
+    request_client: &requests::Requests,
+    target: &target::Target,
+    registry_tree: &requests::GetBaseTreeResponse,
+    path: &str,
+) -> Result<Option<String>, u8> {
+    let Some(file) = registry_tree
+        .tree
+        .iter()
+        .find(|file| file._type == "blob" && file.path == path)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        request_client
+            .get_blob(&target.repository, &file.sha)?
+            .content,
+    ))
+}
+
+// The files are read from the same recursive tree the content of the sources is
+// compared with, so both see the same files
+fn get_extension_files(
+    request_client: &requests::Requests,
+    repository: &str,
+    id: &str,
+    repository_tree: &requests::GetBaseTreeResponse,
+) -> Result<HashMap<String, String>, u8> {
+    println!("Requesting the updated files and creating blobs for {}", id);
+
+    if repository_tree.truncated {
+        eprintln!(
+            "The repository tree was truncated by GitHub, the files of {} can not be requested",
+            id
+        );
+        return Err(0x1);
+    }
+
+    let prefix = format!("{}/", id);
+    let mut files = HashMap::new();
+
+    for file in repository_tree.tree.iter() {
+        if file._type != "blob" {
+            continue;
+        }
+
+        let Some(relative_path) = file.path.strip_prefix(&prefix) else {
+            continue;
+        };
+
+        if !content::is_published_file(relative_path) {
+            continue;
         }
+
+        let blob = request_client.get_blob(repository, &file.sha)?;
+        files.insert(file.path.clone(), blob.content);
     }
 
-    // match request_client.create_commit(registry_update_tree) {
-    //     Ok(response) => (),
-    //     Err(exit_code) => {
-    //         eprintln!("Exiting the program");
-    //         return ExitCode::from(exit_code);
-    //     }
-    // }
+    if !files.contains_key(&format!("{}index.js", prefix)) {
+        eprintln!(
+            "The repository does not contain the bundle {}index.js",
+            prefix
+        );
+        return Err(0x1);
+    }
 
-    // TODO:
-    // V - Get all files of the updated extensions from the extension repository
-    // V - Create a new tree in the registry repository using the new files
-    // - Create a commit using that new tree in the registry repository
-    // - Switch to specific exit codes
-    // - Switch from std prints to tracing
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use base64::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        fixtures::{source, tree, versioning},
+        registry_policy::RegistryPolicy,
+        requests::RefUpdate,
+        versioning::{OutputFormat, SourceOrder, Versioning, ADULT_VERSIONING_PATH},
+    };
+
+    const OUTPUT_FORMAT: OutputFormat = OutputFormat {
+        indent: 2,
+        order: SourceOrder::Id,
+    };
+
+    fn registry_versioning() -> Versioning {
+        let mut adult_source = source("Adult", "1.0.0");
+        adult_source["contentRating"] = json!("ADULT");
+
+        versioning(vec![source("Example", "1.0.0"), adult_source])
+    }
+
+    fn policy(adult_sources: &str) -> RegistryPolicy {
+        serde_json::from_value(json!({ "adultSources": adult_sources })).unwrap()
+    }
+
+    fn listed_ids(files: &HashMap<String, String>, path: &str) -> Vec<String> {
+        let versioning: Versioning =
+            serde_json::from_slice(&BASE64_STANDARD.decode(&files[path]).unwrap()).unwrap();
+
+        versioning
+            .sources()
+            .iter()
+            .map(|source| source.id().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn moved_updates_are_retried_until_they_succeed() {
+        let attempts = Cell::new(0);
+
+        let result = retry_when_moved(|| {
+            attempts.set(attempts.get() + 1);
+
+            match attempts.get() {
+                3 => Ok(RefUpdate::Updated),
+                _ => Ok(RefUpdate::Moved),
+            }
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn retrying_stops_after_the_last_attempt_or_an_error() {
+        let attempts = Cell::new(0);
+
+        let result = retry_when_moved(|| {
+            attempts.set(attempts.get() + 1);
+            Ok(RefUpdate::Moved)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), MAX_UPDATE_ATTEMPTS);
 
-    ExitCode::from(0x0)
+        attempts.set(0);
+
+        let result = retry_when_moved(|| {
+            attempts.set(attempts.get() + 1);
+            Err(0x1)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn adult_sources_are_listed_unless_the_policy_keeps_them_separate() {
+        assert_eq!(
+            listed_versioning(&registry_versioning(), &policy("allow"))
+                .sources()
+                .len(),
+            2
+        );
+        assert_eq!(
+            listed_versioning(&registry_versioning(), &policy("reject"))
+                .sources()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn separate_adult_sources_are_written_to_their_own_versioning_file() {
+        for adult_sources in ["separate", "reject"] {
+            let (files, removed_files) = channel_versioning_files(
+                &registry_versioning(),
+                &policy(adult_sources),
+                &tree(&[]),
+                &OUTPUT_FORMAT,
+            )
+            .unwrap();
+
+            assert_eq!(listed_ids(&files, "versioning.json"), vec!["Example"]);
+            assert_eq!(listed_ids(&files, ADULT_VERSIONING_PATH), vec!["Adult"]);
+            assert!(removed_files.is_empty());
+        }
+    }
+
+    #[test]
+    fn the_separate_versioning_file_is_removed_once_adult_sources_are_allowed() {
+        let (files, removed_files) = channel_versioning_files(
+            &registry_versioning(),
+            &policy("allow"),
+            &tree(&[(ADULT_VERSIONING_PATH, "a")]),
+            &OUTPUT_FORMAT,
+        )
+        .unwrap();
+
+        assert_eq!(
+            listed_ids(&files, "versioning.json"),
+            vec!["Adult", "Example"]
+        );
+        assert!(!files.contains_key(ADULT_VERSIONING_PATH));
+        assert_eq!(removed_files, vec![ADULT_VERSIONING_PATH]);
+    }
 }
@@ -0,0 +1,432 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use base64::prelude::*;
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    get_registry_file,
+    requests::{GetBaseTreeResponse, Requests},
+    target::Target,
+    utils::json::parse_base64_json,
+};
+
+// The manifest lists the SHA-256 checksum of every published file per source, so
+// consumers can verify a source is the one that was published. It is signed when
+// an ed25519 key is provided, the signature is stored next to it in manifest.json.sig.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    generated_at: String,
+    sources: BTreeMap<String, ManifestSource>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestSource {
+    version: String,
+    // Keyed by the path relative to the source directory
+    files: BTreeMap<String, String>,
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Both keys are base64 encoded 32 byte keys, for the signing key this is its seed
+fn decode_key(value: &str) -> Option<[u8; 32]> {
+    BASE64_STANDARD.decode(value.trim()).ok()?.try_into().ok()
+}
+
+pub fn decode_signing_key(value: &str) -> Option<SigningKey> {
+    decode_key(value).map(|bytes| SigningKey::from_bytes(&bytes))
+}
+
+pub fn decode_verifying_key(value: &str) -> Option<VerifyingKey> {
+    decode_key(value).and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+}
+
+impl Manifest {
+    pub fn source(&self, id: &str) -> Option<&ManifestSource> {
+        self.sources.get(id)
+    }
+
+    // Replaces the checksums of a source by the ones of its base64 encoded files
+    pub fn record(
+        &mut self,
+        id: &str,
+        version: &str,
+        files: &HashMap<String, String>,
+    ) -> Result<(), u8> {
+        let prefix = format!("{}/", id);
+        let mut checksums = BTreeMap::new();
+
+        for (path, content) in files.iter() {
+            let Some(relative_path) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+
+            match BASE64_STANDARD.decode(content.replace("\n", "")) {
+                Ok(bytes) => {
+                    checksums.insert(relative_path.to_string(), sha256(&bytes));
+                }
+                Err(err) => {
+                    eprintln!(
+                        "An error occurred while base64 decoding the file {}: {}",
+                        path, &err
+                    );
+                    return Err(0x1);
+                }
+            }
+        }
+
+        self.generated_at = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        self.sources.insert(
+            id.to_string(),
+            ManifestSource {
+                version: version.to_string(),
+                files: checksums,
+            },
+        );
+
+        Ok(())
+    }
+
+    // Restores the checksums of a source from a previous manifest, used when rolling back
+    pub fn restore(&mut self, id: &str, source: Option<ManifestSource>) {
+        self.generated_at = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        match source {
+            Some(source) => self.sources.insert(id.to_string(), source),
+            None => self.sources.remove(id),
+        };
+    }
+
+    // Returns the base64 encoded manifest and, when a signing key is provided, its signature
+    pub fn to_base64(
+        &self,
+        signing_key: Option<&SigningKey>,
+    ) -> Result<(String, Option<String>), u8> {
+        let mut buffer = match serde_json::to_vec_pretty(&self) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                eprintln!(
+                    "An error occurred while serializing the manifest struct: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+        buffer.push(b'\n');
+
+        let signature = signing_key.map(|signing_key| {
+            println!("Signed the manifest file");
+
+            BASE64_STANDARD.encode(format!(
+                "{}\n",
+                BASE64_STANDARD.encode(signing_key.sign(&buffer).to_bytes())
+            ))
+        });
+
+        println!("Encoded the manifest file to base64");
+
+        Ok((BASE64_STANDARD.encode(&buffer), signature))
+    }
+}
+
+// Restores the checksums of a source in the manifest of a channel and creates the blobs of
+// the manifest and its signature, the paths are relative to the channel directory.
+pub fn restore_blobs(
+    request_client: &Requests,
+    target: &Target,
+    registry_tree: &GetBaseTreeResponse,
+    id: &str,
+    source: Option<ManifestSource>,
+    signing_key: Option<&SigningKey>,
+) -> Result<Vec<(String, Option<String>)>, u8> {
+    let mut registry_manifest =
+        match get_registry_file(request_client, target, registry_tree, "manifest.json")? {
+            Some(content) => parse_base64_json(&content, "manifest")?,
+            None => Manifest::default(),
+        };
+
+    registry_manifest.restore(id, source);
+
+    let (manifest_base64, signature_base64) = registry_manifest.to_base64(signing_key)?;
+
+    let mut blobs = vec![];
+
+    let manifest_blob = request_client.create_blob(&target.repository, manifest_base64)?;
+    blobs.push(("manifest.json".to_string(), Some(manifest_blob.sha)));
+
+    match signature_base64 {
+        Some(signature_base64) => {
+            let signature_blob =
+                request_client.create_blob(&target.repository, signature_base64)?;
+            blobs.push(("manifest.json.sig".to_string(), Some(signature_blob.sha)));
+        }
+        // A signature of an earlier manifest would no longer match
+        None => {
+            if registry_tree
+                .tree
+                .iter()
+                .any(|file| file.path == "manifest.json.sig")
+            {
+                blobs.push(("manifest.json.sig".to_string(), None));
+            }
+        }
+    }
+
+    Ok(blobs)
+}
+
+// Lists the static files of a source recursively, as they are published
+fn static_files(directory: &Path, relative_directory: &str, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let relative_path = format!(
+            "{}/{}",
+            relative_directory,
+            entry.file_name().to_string_lossy()
+        );
+
+        if entry.path().is_dir() {
+            static_files(&entry.path(), &relative_path, files);
+        } else if entry.path().is_file() {
+            files.push(relative_path);
+        }
+    }
+}
+
+// Verifies a checkout of the registry against its manifest, every file of a source in
+// the manifest has to match its checksum and no unlisted bundle or static file may exist.
+pub fn verify(path: &str, verifying_key: Option<&VerifyingKey>) -> Result<(), u8> {
+    let root = Path::new(path);
+
+    let manifest_bytes = match fs::read(root.join("manifest.json")) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!(
+                "An error occurred while reading the manifest of the registry checkout {}: {}",
+                path, &err
+            );
+            return Err(0x1);
+        }
+    };
+
+    match (fs::read_to_string(root.join("manifest.json.sig")), verifying_key) {
+        (Ok(raw_signature), Some(verifying_key)) => {
+            let signature = BASE64_STANDARD
+                .decode(raw_signature.trim())
+                .ok()
+                .and_then(|bytes| Signature::from_slice(&bytes).ok());
+
+            match signature {
+                Some(signature) if verifying_key.verify(&manifest_bytes, &signature).is_ok() => {
+                    println!("The signature of the manifest is valid")
+                }
+                _ => {
+                    eprintln!("The signature of the manifest is invalid");
+                    return Err(0x1);
+                }
+            }
+        }
+        (Err(_), Some(_)) => {
+            eprintln!("The manifest is not signed while a public key was provided");
+            return Err(0x1);
+        }
+        (Ok(_), None) => println!(
+            "The manifest is signed but no public key was provided in REGISTRY_MANAGER_PUBLIC_KEY, skipping the signature"
+        ),
+        (Err(_), None) => println!("The manifest is not signed, skipping the signature"),
+    }
+
+    let manifest: Manifest = match serde_json::from_slice(&manifest_bytes) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            eprintln!(
+                "An error occurred while deserializing the manifest to JSON: {}",
+                &err
+            );
+            return Err(0x1);
+        }
+    };
+
+    let mut failed = vec![];
+
+    println!("Verifying the sources of the registry checkout:");
+
+    for (id, source) in manifest.sources.iter() {
+        let mut problems = vec![];
+
+        for (relative_path, checksum) in source.files.iter() {
+            match fs::read(root.join(id).join(relative_path)) {
+                Ok(bytes) if sha256(&bytes) == *checksum => (),
+                Ok(_) => problems.push(format!("the checksum of {} does not match", relative_path)),
+                Err(_) => problems.push(format!("the file {} does not exist", relative_path)),
+            }
+        }
+
+        let mut published_files = vec!["index.js".to_string()];
+        static_files(
+            &root.join(id).join("static"),
+            "static",
+            &mut published_files,
+        );
+
+        for relative_path in published_files {
+            if root.join(id).join(&relative_path).is_file()
+                && !source.files.contains_key(&relative_path)
+            {
+                problems.push(format!("the file {} is not in the manifest", relative_path));
+            }
+        }
+
+        if problems.is_empty() {
+            println!("{} ({}): Passed", id, source.version);
+        } else {
+            println!("{} ({}): Failed", id, source.version);
+
+            for problem in problems.iter() {
+                println!("    {}", problem);
+            }
+
+            failed.push(id.as_str());
+        }
+    }
+
+    if !failed.is_empty() {
+        eprintln!(
+            "The following sources did not pass the verification: {}",
+            failed.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    println!("Verified the registry checkout");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::updated_extensions;
+
+    fn manifest() -> Manifest {
+        let updated_extensions = updated_extensions(&[
+            (
+                "Example",
+                &[
+                    ("index.js", b"exports.Example = {};"),
+                    ("static/icon.png", b"icon"),
+                ],
+            ),
+            ("Other", &[("index.js", b"exports.Other = {};")]),
+        ]);
+
+        let mut manifest = Manifest::default();
+        manifest
+            .record("Example", "1.0.0", &updated_extensions[0].1)
+            .unwrap();
+        manifest
+    }
+
+    // Writes a registry checkout with the manifest and its signature to a new directory
+    fn checkout(name: &str, signing_key: Option<&SigningKey>) -> String {
+        let root =
+            std::env::temp_dir().join(format!("registry-manager-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Example/static")).unwrap();
+
+        fs::write(root.join("Example/index.js"), "exports.Example = {};").unwrap();
+        fs::write(root.join("Example/static/icon.png"), "icon").unwrap();
+
+        let (manifest, signature) = manifest().to_base64(signing_key).unwrap();
+        fs::write(
+            root.join("manifest.json"),
+            BASE64_STANDARD.decode(manifest).unwrap(),
+        )
+        .unwrap();
+
+        if let Some(signature) = signature {
+            fs::write(
+                root.join("manifest.json.sig"),
+                BASE64_STANDARD.decode(signature).unwrap(),
+            )
+            .unwrap();
+        }
+
+        root.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn record_lists_the_checksums_of_the_files_of_the_source() {
+        let manifest = manifest();
+        let source = manifest.source("Example").unwrap();
+
+        assert_eq!(source.version, "1.0.0");
+        assert_eq!(
+            source.files.keys().collect::<Vec<&String>>(),
+            vec!["index.js", "static/icon.png"]
+        );
+        assert_eq!(source.files["static/icon.png"], sha256(b"icon"));
+        assert!(manifest.source("Other").is_none());
+    }
+
+    #[test]
+    fn verify_accepts_an_unchanged_checkout() {
+        let path = checkout("unchanged", None);
+
+        assert!(verify(&path, None).is_ok());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn verify_rejects_changed_and_unlisted_files() {
+        let path = checkout("changed", None);
+
+        fs::write(Path::new(&path).join("Example/index.js"), "changed").unwrap();
+        assert!(verify(&path, None).is_err());
+
+        fs::write(
+            Path::new(&path).join("Example/index.js"),
+            "exports.Example = {};",
+        )
+        .unwrap();
+        fs::write(Path::new(&path).join("Example/static/extra.png"), "extra").unwrap();
+        assert!(verify(&path, None).is_err());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn verify_checks_the_signature_with_the_public_key() {
+        let signing_key = SigningKey::from_bytes(&[1; 32]);
+        let other_key = SigningKey::from_bytes(&[2; 32]);
+        let path = checkout("signed", Some(&signing_key));
+
+        assert!(verify(&path, Some(&signing_key.verifying_key())).is_ok());
+        assert!(verify(&path, Some(&other_key.verifying_key())).is_err());
+
+        fs::remove_dir_all(path).unwrap();
+
+        let path = checkout("unsigned", None);
+
+        assert!(verify(&path, Some(&signing_key.verifying_key())).is_err());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
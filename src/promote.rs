@@ -0,0 +1,287 @@
+use node_semver::Version;
+
+use crate::{
+    channel::Channel,
+    channel_versioning_files, commit_to_registry, derived_files, get_channel_versioning,
+    get_registry_file, get_registry_policy, listed_versioning, manifest,
+    pull_request::Proposal,
+    requests::{RefUpdate, Requests, ResponseFile},
+    retry_when_moved,
+    target::Target,
+    utils::{config::Config, json::parse_base64_json},
+    versioning::SourceChange,
+};
+
+// Copies a source from one channel of the registry to another by committing its registry
+// entry and files to the other channel, the blobs of the files are reused. The archive of
+// the source is not promoted, a prerelease version can not be promoted to stable.
+pub fn run(
+    request_client: &Requests,
+    config: &Config,
+    target: &Target,
+    id: &str,
+    from: Channel,
+    to: Channel,
+) -> Result<(), u8> {
+    if from == to {
+        eprintln!("A source can not be promoted to the channel it is already in");
+        return Err(0x1);
+    }
+
+    retry_when_moved(|| promote(request_client, config, target, id, from, to))?;
+
+    println!("Promoted {} to the {} channel", id, to.name());
+
+    Ok(())
+}
+
+fn promote(
+    request_client: &Requests,
+    config: &Config,
+    target: &Target,
+    id: &str,
+    from: Channel,
+    to: Channel,
+) -> Result<RefUpdate, u8> {
+    let registry_ref = request_client.get_ref(&target.repository, &target.branch)?;
+    let registry_commit =
+        request_client.get_commit(&target.repository, &registry_ref.object.sha)?;
+
+    let registry_tree =
+        request_client.get_tree(&target.repository, &registry_commit.tree.sha, true)?;
+
+    if registry_tree.truncated {
+        eprintln!("The registry tree was truncated by GitHub, the files of the source can not be promoted");
+        return Err(0x1);
+    }
+
+    // The paths of both trees are relative to their channel directory
+    let from_tree = target.tree(from, &registry_tree);
+    let to_tree = target.tree(to, &registry_tree);
+
+    let registry_policy = get_registry_policy(request_client, target, &registry_tree)?;
+
+    let Some(from_versioning) = get_channel_versioning(request_client, target, &from_tree)? else {
+        eprintln!("The {} channel does not exist in the registry", from.name());
+        return Err(0x1);
+    };
+
+    let Some(source) = from_versioning.source(id) else {
+        eprintln!(
+            "The source {} does not exist in the {} channel of the registry",
+            id,
+            from.name()
+        );
+        return Err(0x1);
+    };
+    let version = source.version().to_string();
+
+    let parsed_version = match version.parse::<Version>() {
+        Ok(parsed_version) => parsed_version,
+        Err(err) => {
+            eprintln!(
+                "The registry version {} of {} could not be parsed: {}",
+                &version, id, &err
+            );
+            return Err(0x1);
+        }
+    };
+
+    if parsed_version.is_prerelease() && !to.allows_prerelease() {
+        eprintln!(
+            "The version {} of {} is a prerelease, which can not be promoted to the {} channel",
+            &version,
+            id,
+            to.name()
+        );
+        return Err(0x1);
+    }
+
+    // The source was published before the current registry policy, so it is checked again
+    let violations = registry_policy.violations(
+        source
+            .build()
+            .map(|build| build.repository())
+            .unwrap_or_default(),
+        source,
+    );
+
+    if !violations.is_empty() {
+        eprintln!(
+            "The source {} violates the policy of the registry: {}",
+            id,
+            violations.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    let mut to_versioning = match get_channel_versioning(request_client, target, &to_tree)? {
+        Some(to_versioning) => to_versioning,
+        None => {
+            println!(
+                "The {} channel does not exist in the registry yet, creating it",
+                to.name()
+            );
+            from_versioning.without_sources()
+        }
+    };
+
+    let to_version = to_versioning
+        .source(id)
+        .map(|to_source| to_source.version().to_string());
+
+    if let Some(to_version) = &to_version {
+        if to_version
+            .parse::<Version>()
+            .is_ok_and(|to_version| to_version >= parsed_version)
+        {
+            eprintln!(
+                "The {} channel already contains {} at version {}, which is not older than {}",
+                to.name(),
+                id,
+                to_version,
+                &version
+            );
+            return Err(0x1);
+        }
+    }
+
+    println!(
+        "Promoting {} {} from the {} channel to the {} channel",
+        id,
+        &version,
+        from.name(),
+        to.name()
+    );
+
+    let prefix = format!("{}/", id);
+    let archive_prefix = format!("{}/versions/", id);
+
+    // Files of an earlier version in the other channel which are not part of
+    // the promoted version are removed, the archive is left untouched
+    let is_source_file = |file: &&ResponseFile| {
+        file._type == "blob"
+            && file.path.starts_with(&prefix)
+            && !file.path.starts_with(&archive_prefix)
+    };
+
+    let mut blobs: Vec<(String, Option<String>)> = from_tree
+        .tree
+        .iter()
+        .filter(is_source_file)
+        .map(|file| (file.path.clone(), Some(file.sha.clone())))
+        .collect();
+
+    for file in to_tree.tree.iter().filter(is_source_file) {
+        if !blobs.iter().any(|(path, _)| *path == file.path) {
+            blobs.push((file.path.clone(), None));
+        }
+    }
+
+    let change = SourceChange::restored(source, to_version.as_deref());
+    // The changelog records the promotion for the repository the source was published from
+    let source_repository = source
+        .build()
+        .map(|build| build.repository().to_string())
+        .unwrap_or(target.repository.clone());
+
+    to_versioning.restore_source(source.clone());
+
+    let (mut files, removed_files) = channel_versioning_files(
+        &to_versioning,
+        &registry_policy,
+        &to_tree,
+        &config.output_format,
+    )?;
+
+    let (derived_files, removed_derived_files) = derived_files(
+        request_client,
+        config,
+        target,
+        &to_tree,
+        &listed_versioning(&to_versioning, &registry_policy),
+        &source_repository,
+        &[change],
+        None,
+    )?;
+
+    files.extend(derived_files);
+
+    for (path, content) in files {
+        let blob = request_client.create_blob(&target.repository, content)?;
+        blobs.push((path, Some(blob.sha)));
+    }
+
+    blobs.extend(
+        removed_files
+            .into_iter()
+            .chain(removed_derived_files)
+            .map(|path| (path, None)),
+    );
+
+    // The checksums of the promoted files are identical in both channels
+    if config.manifest {
+        let from_manifest_source =
+            match get_registry_file(request_client, target, &from_tree, "manifest.json")? {
+                Some(content) => parse_base64_json::<manifest::Manifest>(&content, "manifest")?
+                    .source(id)
+                    .cloned(),
+                None => None,
+            };
+
+        if from_manifest_source.is_none() {
+            eprintln!(
+                "The manifest of the {} channel does not contain {}, its checksums are removed from the manifest",
+                from.name(),
+                id
+            );
+        }
+
+        blobs.extend(manifest::restore_blobs(
+            request_client,
+            target,
+            &to_tree,
+            id,
+            from_manifest_source,
+            config.signing_key.as_ref(),
+        )?);
+    }
+
+    let blobs = blobs
+        .into_iter()
+        .map(|(path, sha)| (target.path(to, &path), sha))
+        .collect();
+
+    let promote_tree =
+        request_client.create_tree_from_blobs(&target.repository, registry_tree.sha, blobs)?;
+
+    let title = format!(
+        "Promote {} {} from {} to {}",
+        id,
+        &version,
+        from.name(),
+        to.name()
+    );
+    let description = format!(
+        "Copies the registry entry and the files of {} from the {} channel to the {} channel.",
+        id,
+        from.name(),
+        to.name()
+    );
+
+    commit_to_registry(
+        request_client,
+        config,
+        target,
+        to,
+        promote_tree.sha,
+        registry_commit.sha,
+        format!("{}\n\n{}", title, description),
+        Proposal {
+            kind: "promote",
+            subject: id.to_string(),
+            title,
+            body: description,
+        },
+    )
+}
@@ -0,0 +1,229 @@
+use crate::{
+    channel::Channel,
+    requests::{self, Requests},
+    target::Target,
+    versioning::{Outcome, SourceChange},
+};
+
+// A change of the registry which is proposed as a pull request, every kind
+// of change has its own branch per subject, e.g. per repository or source
+pub struct Proposal {
+    pub kind: &'static str,
+    pub subject: String,
+    pub title: String,
+    pub body: String,
+}
+
+// Publishes the commit on a separate branch of the registry and opens a pull request
+// for it, an open pull request of the same proposal is updated instead.
+pub fn open(
+    request_client: &Requests,
+    target: &Target,
+    channel: Channel,
+    proposal: Proposal,
+    commit_sha: String,
+) -> Result<(), u8> {
+    let registry_repository = &target.repository;
+    let base_branch = &target.branch;
+    let branch = branch(target, channel, proposal.kind, &proposal.subject);
+    let owner = registry_repository
+        .split('/')
+        .next()
+        .unwrap_or(registry_repository);
+
+    let open_pull_request = request_client
+        .get_pull_requests(
+            registry_repository,
+            &format!("{}:{}", owner, branch),
+            base_branch,
+        )?
+        .into_iter()
+        .next();
+
+    // The commit is based on the current registry instead of on the
+    // previous commit of the branch, so the branch has to be forced
+    let force_branch = |commit_sha: String| match request_client.update_ref(
+        registry_repository,
+        &branch,
+        commit_sha,
+        true,
+    )? {
+        requests::RefUpdate::Updated => Ok(()),
+        requests::RefUpdate::Moved => {
+            eprintln!(
+                "The branch of the pull request could not be updated: {}",
+                &branch
+            );
+            Err(0x1)
+        }
+    };
+
+    match open_pull_request {
+        Some(pull_request) => {
+            force_branch(commit_sha)?;
+
+            request_client.update_pull_request(
+                registry_repository,
+                pull_request.number,
+                proposal.title,
+                proposal.body,
+            )?;
+        }
+        None => {
+            // The branch of a closed pull request is reused
+            if !request_client.create_ref(registry_repository, &branch, commit_sha.clone())? {
+                force_branch(commit_sha)?;
+            }
+
+            request_client.create_pull_request(
+                registry_repository,
+                proposal.title,
+                branch.clone(),
+                base_branch.clone(),
+                proposal.body,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// Every channel and every registry directory has its own branch, as they are merged independently
+fn branch(target: &Target, channel: Channel, kind: &str, subject: &str) -> String {
+    match channel {
+        Channel::Stable => format!("{}/{}{}", kind, target.prefix(), subject),
+        _ => format!("{}-{}/{}{}", kind, channel.name(), target.prefix(), subject),
+    }
+}
+
+// The proposal of publishing the updated extensions of a repository, the removed
+// files are the paths in the registry repository
+pub fn publish_proposal(
+    repository: &str,
+    channel: Channel,
+    changes: &[SourceChange],
+    removed_files: &[String],
+) -> Proposal {
+    let title = match channel {
+        Channel::Stable => format!("Publish the extensions of {}", repository),
+        _ => format!(
+            "Publish the extensions of {} to the {} channel",
+            repository,
+            channel.name()
+        ),
+    };
+
+    Proposal {
+        kind: "publish",
+        subject: repository
+            .rsplit('/')
+            .next()
+            .unwrap_or(repository)
+            .to_string(),
+        title,
+        body: body(repository, changes, removed_files),
+    }
+}
+
+fn body(repository: &str, changes: &[SourceChange], removed_files: &[String]) -> String {
+    let mut body = format!(
+        "Publishes the extensions of {} to the registry.\n",
+        repository
+    );
+
+    let sections = [
+        ("Added", Outcome::Added),
+        ("Updated", Outcome::Updated),
+        ("Downgraded", Outcome::Downgraded { allowed: true }),
+        ("Metadata updated", Outcome::MetadataUpdated),
+    ];
+
+    for (title, outcome) in sections {
+        let lines: Vec<String> = changes
+            .iter()
+            .filter(|change| change.outcome == outcome)
+            .map(|change| match &change.old_version {
+                Some(old_version) if *old_version != change.new_version => format!(
+                    "- {} (`{}`): {} → {}",
+                    change.name, change.id, old_version, change.new_version
+                ),
+                _ => format!(
+                    "- {} (`{}`): {}",
+                    change.name, change.id, change.new_version
+                ),
+            })
+            .collect();
+
+        if lines.is_empty() {
+            continue;
+        }
+
+        body.push_str(&format!("\n### {}\n\n{}\n", title, lines.join("\n")));
+    }
+
+    if !removed_files.is_empty() {
+        let mut removed_files = removed_files.to_vec();
+        removed_files.sort();
+
+        body.push_str(&format!(
+            "\n### Removed files\n\n{}\n",
+            removed_files
+                .iter()
+                .map(|path| format!("- `{}`", path))
+                .collect::<Vec<String>>()
+                .join("\n")
+        ));
+    }
+
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::change, target::parse_targets};
+
+    #[test]
+    fn every_channel_and_registry_directory_has_its_own_branch() {
+        let targets = parse_targets(
+            r#"[{"repository": "org/registry"}, {"repository": "org/registry", "prefix": "ext"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            branch(&targets[0], Channel::Stable, "publish", "extensions"),
+            "publish/extensions"
+        );
+        assert_eq!(
+            branch(&targets[1], Channel::Nightly, "rollback", "Example"),
+            "rollback-nightly/ext/Example"
+        );
+    }
+
+    #[test]
+    fn the_body_lists_the_changes_and_removed_files_per_section() {
+        let proposal = publish_proposal(
+            "paperback-community/extensions",
+            Channel::Beta,
+            &[
+                change("A", Some("1.0.0"), "1.1.0", Outcome::Updated),
+                change("B", None, "1.0.0", Outcome::Added),
+                change("C", Some("1.0.0"), "1.0.0", Outcome::Unchanged),
+            ],
+            &[
+                "beta/A/static/old.png".to_string(),
+                "beta/A/a.png".to_string(),
+            ],
+        );
+
+        assert_eq!(proposal.subject, "extensions");
+        assert_eq!(
+            proposal.title,
+            "Publish the extensions of paperback-community/extensions to the beta channel"
+        );
+        assert_eq!(
+            proposal.body,
+            "Publishes the extensions of paperback-community/extensions to the registry.\n\n### Added\n\n- B (`B`): 1.0.0\n\n### Updated\n\n- A (`A`): 1.0.0 → 1.1.0\n\n### Removed files\n\n- `beta/A/a.png`\n- `beta/A/static/old.png`\n"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::versioning::{ContentRating, Source};
+
+// The policy file is stored at the root of the registry, next to the stable channel
+pub const PATH: &str = "registry-policy.json";
+
+// The rules of the registry maintainers, every rule is optional. Repositories are either
+// written out or end with a wildcard, e.g. "paperback-community/*".
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct RegistryPolicy {
+    allowed_repositories: Option<Vec<String>>,
+    id_prefixes: HashMap<String, Vec<String>>,
+    content_ratings: Option<Vec<ContentRating>>,
+    adult_sources: AdultSources,
+    required_badges: Vec<String>,
+    max_icon_size: Option<usize>,
+    max_static_size: Option<usize>,
+    max_bundle_size: Option<usize>,
+}
+
+// How sources with the ADULT content rating are listed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdultSources {
+    #[default]
+    Allow,
+    // Listed in a separate versioning file of the channel
+    Separate,
+    // Not accepted, adult sources which were published before are
+    // kept in the separate versioning file but never listed
+    Reject,
+}
+
+fn matches_repository(pattern: &str, repository: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => repository.starts_with(prefix),
+        None => pattern == repository,
+    }
+}
+
+impl RegistryPolicy {
+    pub fn adult_sources(&self) -> AdultSources {
+        self.adult_sources
+    }
+
+    pub fn max_icon_size(&self) -> Option<usize> {
+        self.max_icon_size
+    }
+
+    pub fn max_static_size(&self) -> Option<usize> {
+        self.max_static_size
+    }
+
+    pub fn max_bundle_size(&self) -> Option<usize> {
+        self.max_bundle_size
+    }
+
+    pub fn allows_repository(&self, repository: &str) -> bool {
+        self.allowed_repositories.as_ref().is_none_or(|patterns| {
+            patterns
+                .iter()
+                .any(|pattern| matches_repository(pattern, repository))
+        })
+    }
+
+    // Returns the rules the source of the repository does not comply with
+    pub fn violations(&self, repository: &str, source: &Source) -> Vec<String> {
+        let mut violations = vec![];
+
+        let prefixes: Vec<&String> = self
+            .id_prefixes
+            .iter()
+            .filter(|(pattern, _)| matches_repository(pattern, repository))
+            .flat_map(|(_, prefixes)| prefixes)
+            .collect();
+
+        if !prefixes.is_empty()
+            && !prefixes
+                .iter()
+                .any(|prefix| source.id().starts_with(prefix.as_str()))
+        {
+            violations.push(format!(
+                "the id does not start with one of the prefixes allowed for {}: {}",
+                repository,
+                prefixes
+                    .iter()
+                    .map(|prefix| prefix.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ));
+        }
+
+        if let Some(content_ratings) = &self.content_ratings {
+            if !content_ratings
+                .iter()
+                .any(|rating| *rating == source.content_rating())
+            {
+                violations.push(format!(
+                    "the content rating {} is not allowed, allowed are: {}",
+                    source.content_rating().as_str(),
+                    content_ratings
+                        .iter()
+                        .map(ContentRating::as_str)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ));
+            }
+        }
+
+        if self.adult_sources == AdultSources::Reject
+            && source.content_rating() == ContentRating::Adult
+        {
+            violations.push("sources with the ADULT content rating are not allowed".to_string());
+        }
+
+        for required_badge in self.required_badges.iter() {
+            if !source
+                .badges()
+                .any(|badge| badge.label().eq_ignore_ascii_case(required_badge))
+            {
+                violations.push(format!("the required badge {} is missing", required_badge));
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{source, versioning};
+
+    fn policy(policy: serde_json::Value) -> RegistryPolicy {
+        serde_json::from_value(policy).unwrap()
+    }
+
+    fn violations(
+        registry_policy: &RegistryPolicy,
+        repository: &str,
+        source: serde_json::Value,
+    ) -> Vec<String> {
+        let id = source["id"].as_str().unwrap().to_string();
+        let versioning = versioning(vec![source]);
+
+        registry_policy.violations(repository, versioning.source(&id).unwrap())
+    }
+
+    #[test]
+    fn repositories_are_matched_exactly_or_by_their_wildcard() {
+        let registry_policy = policy(json!({
+            "allowedRepositories": ["paperback-community/*", "someone/extensions"]
+        }));
+
+        assert!(registry_policy.allows_repository("paperback-community/extensions"));
+        assert!(registry_policy.allows_repository("someone/extensions"));
+        assert!(!registry_policy.allows_repository("someone/other"));
+        assert!(RegistryPolicy::default().allows_repository("someone/other"));
+    }
+
+    #[test]
+    fn ids_have_to_start_with_a_prefix_of_their_repository() {
+        let registry_policy = policy(json!({
+            "idPrefixes": { "paperback-community/*": ["Community"] }
+        }));
+
+        assert!(violations(
+            &registry_policy,
+            "paperback-community/extensions",
+            source("CommunityExample", "1.0.0")
+        )
+        .is_empty());
+        assert_eq!(
+            violations(
+                &registry_policy,
+                "paperback-community/extensions",
+                source("Example", "1.0.0")
+            ),
+            vec!["the id does not start with one of the prefixes allowed for paperback-community/extensions: Community"]
+        );
+        // The prefixes only apply to the matching repositories
+        assert!(violations(
+            &registry_policy,
+            "someone/extensions",
+            source("Example", "1.0.0")
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn content_ratings_and_badges_are_checked() {
+        let registry_policy = policy(json!({
+            "contentRatings": ["EVERYONE"],
+            "requiredBadges": ["Official"]
+        }));
+
+        let mut mature_source = source("Example", "1.0.0");
+        mature_source["contentRating"] = json!("MATURE");
+
+        assert_eq!(
+            violations(&registry_policy, "someone/extensions", mature_source),
+            vec![
+                "the content rating MATURE is not allowed, allowed are: EVERYONE",
+                "the required badge Official is missing"
+            ]
+        );
+
+        let mut badged_source = source("Example", "1.0.0");
+        badged_source["badges"] = json!([{
+            "label": "official",
+            "textColor": "#FFFFFF",
+            "backgroundColor": "#000000"
+        }]);
+
+        assert!(violations(&registry_policy, "someone/extensions", badged_source).is_empty());
+    }
+
+    #[test]
+    fn adult_sources_are_only_a_violation_when_rejected() {
+        let mut adult_source = source("Example", "1.0.0");
+        adult_source["contentRating"] = json!("ADULT");
+
+        assert!(violations(
+            &policy(json!({ "adultSources": "separate" })),
+            "someone/extensions",
+            adult_source.clone()
+        )
+        .is_empty());
+        assert_eq!(
+            violations(
+                &policy(json!({ "adultSources": "reject" })),
+                "someone/extensions",
+                adult_source
+            ),
+            vec!["sources with the ADULT content rating are not allowed"]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, env, time::Duration};
+use std::{collections::HashMap, time::Duration};
 
 use reqwest::{
     blocking::Client,
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 // This causes there to be unused fields which then throw a dead
 // code warning, these warnings may be suppressed.
 
+#[allow(dead_code, clippy::large_enum_variant)]
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 pub enum GetContent {
@@ -71,7 +72,7 @@ pub struct GetBaseTreeResponse {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct ResponseFile {
     pub path: String,
     pub mode: String,
@@ -94,7 +95,31 @@ struct RequestFile {
     pub mode: String,
     #[serde(rename = "type")]
     pub _type: String,
+    // A missing sha removes the file from the base tree
+    pub sha: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateBlobRequestBody {
+    pub content: String,
+    pub encoding: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CreateBlobResponse {
+    pub sha: String,
+    pub url: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct GetBlobResponse {
+    pub sha: String,
+    pub size: u64,
+    pub url: String,
     pub content: String,
+    pub encoding: String,
 }
 
 #[allow(dead_code)]
@@ -106,11 +131,132 @@ pub struct CreateTreeResponse {
     pub tree: Vec<ResponseFile>,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct GetRefResponse {
+    #[serde(rename = "ref")]
+    pub _ref: String,
+    pub url: String,
+    pub object: GitObject,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct GitObject {
+    #[serde(rename = "type")]
+    pub _type: String,
+    pub sha: String,
+    pub url: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct GetCommitResponse {
+    pub sha: String,
+    pub url: String,
+    pub message: String,
+    pub tree: CommitTree,
+    pub parents: Vec<CommitParent>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CommitTree {
+    pub sha: String,
+    pub url: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CommitParent {
+    pub sha: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateCommitRequestBody {
+    pub message: String,
+    pub tree: String,
+    pub parents: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateRefRequestBody {
+    pub sha: String,
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateRefRequestBody {
+    #[serde(rename = "ref")]
+    pub _ref: String,
+    pub sha: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: String,
+    pub html_url: String,
+    pub title: String,
+    pub head: PullRequestRef,
+    pub base: PullRequestRef,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct PullRequestRef {
+    #[serde(rename = "ref")]
+    pub _ref: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreatePullRequestBody {
+    pub title: String,
+    pub head: String,
+    pub base: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdatePullRequestBody {
+    pub title: String,
+    pub body: String,
+}
+
+// The result of a non forced ref update, the ref has moved
+// when another commit was pushed after the parent was read.
+#[derive(Debug, PartialEq)]
+pub enum RefUpdate {
+    Updated,
+    Moved,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ErrorResponse {
+    pub message: String,
+}
+
+// The commits are listed per page, rollback pages through the
+// history until the requested version is found
+pub const COMMITS_PER_PAGE: usize = 100;
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CommitListItem {
+    pub sha: String,
+    pub url: String,
+    pub html_url: String,
+}
+
 pub struct Requests {
     client: Client,
 }
 
-pub fn new() -> Result<Requests, u8> {
+pub fn new(pat: &str) -> Result<Requests, u8> {
     let mut headers = HeaderMap::new();
     headers.insert(
         "Accept",
@@ -122,10 +268,7 @@ pub fn new() -> Result<Requests, u8> {
     );
     headers.insert(
         "Authorization",
-        HeaderValue::from_str(
-            format!("Bearer {}", env::var("REGISTRY_MANAGER_PAT").unwrap()).as_str(),
-        )
-        .unwrap(),
+        HeaderValue::from_str(format!("Bearer {}", pat).as_str()).unwrap(),
     );
 
     match Client::builder()
@@ -149,12 +292,7 @@ pub fn new() -> Result<Requests, u8> {
 }
 
 impl Requests {
-    pub fn get_files(
-        &self,
-        repository: &String,
-        path: &String,
-        branch: &String,
-    ) -> Result<GetContent, u8> {
+    pub fn get_files(&self, repository: &str, path: &str, branch: &str) -> Result<GetContent, u8> {
         let p_response = self
             .client
             .get(format!(
@@ -174,6 +312,19 @@ impl Requests {
                 }
 
                 match raw_response.json::<GetContent>() {
+                    Ok(GetContent::Struct(mut response)) => {
+                        println!(
+                            "Requested the file(s) which match the following repository and path: {}/{}",
+                            &repository, &path
+                        );
+
+                        // The content of files larger than 1 MB is only returned as a blob
+                        if response.encoding == "none" {
+                            response.content = self.get_blob(repository, &response.sha)?.content;
+                        }
+
+                        Ok(GetContent::Struct(response))
+                    }
                     Ok(response) => {
                         println!(
                             "Requested the file(s) which match the following repository and path: {}/{}",
@@ -199,16 +350,20 @@ impl Requests {
 
     pub fn get_tree(
         &self,
-        repository: &String,
-        sha_ref: &String,
+        repository: &str,
+        sha_ref: &str,
+        recursive: bool,
     ) -> Result<GetBaseTreeResponse, u8> {
-        let p_response = self
-            .client
-            .get(format!(
-                "https://api.github.com/repos/{}/git/trees/{}",
-                &repository, &sha_ref
-            ))
-            .send();
+        let mut url = format!(
+            "https://api.github.com/repos/{}/git/trees/{}",
+            &repository, &sha_ref
+        );
+
+        if recursive {
+            url.push_str("?recursive=1");
+        }
+
+        let p_response = self.client.get(url).send();
 
         match p_response {
             Ok(raw_response) => {
@@ -246,36 +401,51 @@ impl Requests {
 
     pub fn create_tree(
         &self,
+        repository: &str,
         base_tree: String,
-        updated_extensions: Vec<(String, HashMap<String, String>)>,
+        files: HashMap<String, String>,
+        removed_files: Vec<String>,
     ) -> Result<CreateTreeResponse, u8> {
-        let mut tree = vec![];
-        for updated_extension in updated_extensions {
-            for updated_extension_file in updated_extension.1.keys() {
-                let file = RequestFile {
-                    path: updated_extension_file.to_string(),
-                    mode: "100644".to_string(),
-                    _type: "blob".to_string(),
-                    content: updated_extension
-                        .1
-                        .get(&updated_extension_file.to_string())
-                        .unwrap()
-                        .to_string(),
-                };
-
-                tree.push(file);
-            }
+        let mut blobs: Vec<(String, Option<String>)> =
+            removed_files.into_iter().map(|path| (path, None)).collect();
+        for (path, content) in files {
+            // The content is base64 encoded, creating the blobs separately keeps
+            // the blob shas identical to the ones in the extension repository
+            let blob = self.create_blob(repository, content)?;
+
+            blobs.push((path, Some(blob.sha)));
         }
 
+        self.create_tree_from_blobs(repository, base_tree, blobs)
+    }
+
+    pub fn create_tree_from_blobs(
+        &self,
+        repository: &str,
+        base_tree: String,
+        blobs: Vec<(String, Option<String>)>,
+    ) -> Result<CreateTreeResponse, u8> {
+        let tree = blobs
+            .into_iter()
+            .map(|(path, sha)| RequestFile {
+                path,
+                mode: "100644".to_string(),
+                _type: "blob".to_string(),
+                sha,
+            })
+            .collect();
+
         let body = CreateTreeRequestBody { base_tree, tree };
 
         let p_body_string = serde_json::to_string(&body);
 
-        let p_response;
-        match p_body_string {
-            Ok(body_string) => p_response = self
+        let p_response = match p_body_string {
+            Ok(body_string) => self
                 .client
-                .post("https://api.github.com/repos/paperback-community/extensions-test/git/trees")
+                .post(format!(
+                    "https://api.github.com/repos/{}/git/trees",
+                    &repository
+                ))
                 .body(body_string)
                 .send(),
             Err(err) => {
@@ -285,7 +455,7 @@ impl Requests {
                 );
                 return Err(0x1);
             }
-        }
+        };
 
         match p_response {
             Ok(raw_response) => {
@@ -319,4 +489,627 @@ impl Requests {
             }
         }
     }
+
+    pub fn create_blob(&self, repository: &str, content: String) -> Result<CreateBlobResponse, u8> {
+        let body = CreateBlobRequestBody {
+            content: content.replace("\n", ""),
+            encoding: "base64".to_string(),
+        };
+
+        let p_response = match serde_json::to_string(&body) {
+            Ok(body_string) => self
+                .client
+                .post(format!(
+                    "https://api.github.com/repos/{}/git/blobs",
+                    &repository
+                ))
+                .body(body_string)
+                .send(),
+            Err(err) => {
+                eprintln!(
+                    "Something went wrong while serializing the request body to JSON: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 201 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<CreateBlobResponse>() {
+                    Ok(response) => Ok(response),
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    // Unlike the contents API, blobs are returned up to a size of 100 MB
+    pub fn get_blob(&self, repository: &str, sha: &str) -> Result<GetBlobResponse, u8> {
+        let p_response = self
+            .client
+            .get(format!(
+                "https://api.github.com/repos/{}/git/blobs/{}",
+                &repository, &sha
+            ))
+            .send();
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 200 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<GetBlobResponse>() {
+                    Ok(response) => {
+                        println!(
+                            "Requested the blob which matches the following repository and sha: {}, {}",
+                            &repository, &sha
+                        );
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn get_ref(&self, repository: &str, branch: &str) -> Result<GetRefResponse, u8> {
+        let p_response = self
+            .client
+            .get(format!(
+                "https://api.github.com/repos/{}/git/ref/heads/{}",
+                &repository, &branch
+            ))
+            .send();
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 200 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<GetRefResponse>() {
+                    Ok(response) => {
+                        println!(
+                            "Requested the ref which matches the following repository and branch: {}, {}",
+                            &repository, &branch
+                        );
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn get_commit(&self, repository: &str, sha: &str) -> Result<GetCommitResponse, u8> {
+        let p_response = self
+            .client
+            .get(format!(
+                "https://api.github.com/repos/{}/git/commits/{}",
+                &repository, &sha
+            ))
+            .send();
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 200 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<GetCommitResponse>() {
+                    Ok(response) => {
+                        println!(
+                            "Requested the commit which matches the following repository and sha: {}, {}",
+                            &repository, &sha
+                        );
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn create_commit(
+        &self,
+        repository: &str,
+        message: String,
+        tree: String,
+        parent: String,
+    ) -> Result<GetCommitResponse, u8> {
+        let body = CreateCommitRequestBody {
+            message,
+            tree,
+            parents: vec![parent],
+        };
+
+        let p_response = match serde_json::to_string(&body) {
+            Ok(body_string) => self
+                .client
+                .post(format!(
+                    "https://api.github.com/repos/{}/git/commits",
+                    &repository
+                ))
+                .body(body_string)
+                .send(),
+            Err(err) => {
+                eprintln!(
+                    "Something went wrong while serializing the request body to JSON: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 201 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<GetCommitResponse>() {
+                    Ok(response) => {
+                        println!(
+                            "Created a commit for the updated extensions: {}",
+                            &response.sha
+                        );
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn update_ref(
+        &self,
+        repository: &str,
+        branch: &str,
+        sha: String,
+        force: bool,
+    ) -> Result<RefUpdate, u8> {
+        // Without force the update is rejected when the commit
+        // is not a descendant of the commit the ref points to
+        let body = UpdateRefRequestBody { sha, force };
+
+        let p_response = match serde_json::to_string(&body) {
+            Ok(body_string) => self
+                .client
+                .patch(format!(
+                    "https://api.github.com/repos/{}/git/refs/heads/{}",
+                    &repository, &branch
+                ))
+                .body(body_string)
+                .send(),
+            Err(err) => {
+                eprintln!(
+                    "Something went wrong while serializing the request body to JSON: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+
+        match p_response {
+            Ok(raw_response) => match raw_response.status().as_u16() {
+                200 => {
+                    println!(
+                        "Updated the ref which matches the following repository and branch: {}, {}",
+                        &repository, &branch
+                    );
+                    Ok(RefUpdate::Updated)
+                }
+                422 => {
+                    let message = raw_response
+                        .json::<ErrorResponse>()
+                        .map(|response| response.message)
+                        .unwrap_or_default();
+
+                    if !is_not_fast_forward(&message) {
+                        eprintln!(
+                            "The ref which matches the following repository and branch could not be updated: {}, {}: {}",
+                            &repository, &branch, &message
+                        );
+                        return Err(0x1);
+                    }
+
+                    println!(
+                        "The ref which matches the following repository and branch has moved: {}, {}",
+                        &repository, &branch
+                    );
+                    Ok(RefUpdate::Moved)
+                }
+                status => {
+                    eprintln!("The response was undesired, status code: {}", &status);
+                    Err(0x1)
+                }
+            },
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    // Returns false when the branch already exists, e.g. the branch of a closed pull request
+    pub fn create_ref(&self, repository: &str, branch: &str, sha: String) -> Result<bool, u8> {
+        let body = CreateRefRequestBody {
+            _ref: format!("refs/heads/{}", &branch),
+            sha,
+        };
+
+        let p_response = match serde_json::to_string(&body) {
+            Ok(body_string) => self
+                .client
+                .post(format!(
+                    "https://api.github.com/repos/{}/git/refs",
+                    &repository
+                ))
+                .body(body_string)
+                .send(),
+            Err(err) => {
+                eprintln!(
+                    "Something went wrong while serializing the request body to JSON: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+
+        match p_response {
+            Ok(raw_response) => match raw_response.status().as_u16() {
+                201 => {
+                    println!(
+                        "Created the ref which matches the following repository and branch: {}, {}",
+                        &repository, &branch
+                    );
+                    Ok(true)
+                }
+                422 => {
+                    let message = raw_response
+                        .json::<ErrorResponse>()
+                        .map(|response| response.message)
+                        .unwrap_or_default();
+
+                    if message != "Reference already exists" {
+                        eprintln!(
+                            "The ref which matches the following repository and branch could not be created: {}, {}: {}",
+                            &repository, &branch, &message
+                        );
+                        return Err(0x1);
+                    }
+
+                    println!(
+                        "The ref which matches the following repository and branch already exists: {}, {}",
+                        &repository, &branch
+                    );
+                    Ok(false)
+                }
+                status => {
+                    eprintln!("The response was undesired, status code: {}", &status);
+                    Err(0x1)
+                }
+            },
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    // The head is filtered by GitHub as <owner>:<branch>, at most one pull request
+    // is open for a head and base branch
+    pub fn get_pull_requests(
+        &self,
+        repository: &str,
+        head: &str,
+        base: &str,
+    ) -> Result<Vec<PullRequest>, u8> {
+        let p_response = self
+            .client
+            .get(format!(
+                "https://api.github.com/repos/{}/pulls?state=open&head={}&base={}",
+                &repository, &head, &base
+            ))
+            .send();
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 200 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<Vec<PullRequest>>() {
+                    Ok(response) => {
+                        println!(
+                            "Requested the open pull requests of the following repository and head: {}, {}",
+                            &repository, &head
+                        );
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn create_pull_request(
+        &self,
+        repository: &str,
+        title: String,
+        head: String,
+        base: String,
+        body: String,
+    ) -> Result<PullRequest, u8> {
+        let body = CreatePullRequestBody {
+            title,
+            head,
+            base,
+            body,
+        };
+
+        let p_response = match serde_json::to_string(&body) {
+            Ok(body_string) => self
+                .client
+                .post(format!(
+                    "https://api.github.com/repos/{}/pulls",
+                    &repository
+                ))
+                .body(body_string)
+                .send(),
+            Err(err) => {
+                eprintln!(
+                    "Something went wrong while serializing the request body to JSON: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 201 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<PullRequest>() {
+                    Ok(response) => {
+                        println!("Created the pull request {}", &response.html_url);
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn update_pull_request(
+        &self,
+        repository: &str,
+        number: u64,
+        title: String,
+        body: String,
+    ) -> Result<PullRequest, u8> {
+        let body = UpdatePullRequestBody { title, body };
+
+        let p_response = match serde_json::to_string(&body) {
+            Ok(body_string) => self
+                .client
+                .patch(format!(
+                    "https://api.github.com/repos/{}/pulls/{}",
+                    &repository, &number
+                ))
+                .body(body_string)
+                .send(),
+            Err(err) => {
+                eprintln!(
+                    "Something went wrong while serializing the request body to JSON: {}",
+                    &err
+                );
+                return Err(0x1);
+            }
+        };
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 200 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<PullRequest>() {
+                    Ok(response) => {
+                        println!("Updated the pull request {}", &response.html_url);
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+
+    pub fn get_commits(
+        &self,
+        repository: &str,
+        path: &str,
+        sha_ref: &str,
+        page: usize,
+    ) -> Result<Vec<CommitListItem>, u8> {
+        let p_response = self
+            .client
+            .get(format!(
+                "https://api.github.com/repos/{}/commits?path={}&sha={}&per_page={}&page={}",
+                &repository, &path, &sha_ref, COMMITS_PER_PAGE, &page
+            ))
+            .send();
+
+        match p_response {
+            Ok(raw_response) => {
+                if raw_response.status() != 200 {
+                    eprintln!(
+                        "The response was undesired, status code: {}",
+                        &raw_response.status(),
+                    );
+                    return Err(0x1);
+                }
+
+                match raw_response.json::<Vec<CommitListItem>>() {
+                    Ok(response) => {
+                        println!(
+                            "Requested page {} of the commits which changed the following repository and path: {}/{}",
+                            &page, &repository, &path
+                        );
+                        Ok(response)
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "Something went wrong while deserializing the response to JSON: {}",
+                            &err
+                        );
+                        Err(0x1)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Something went wrong while making the request: {}", &err);
+                Err(0x1)
+            }
+        }
+    }
+}
+
+// GitHub rejects a non forced ref update with this message when the commit is not a
+// descendant of the commit the ref points to, any other rejection is an error
+fn is_not_fast_forward(message: &str) -> bool {
+    message == "Update is not a fast forward"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_rejected_fast_forward_is_a_moved_ref() {
+        assert!(is_not_fast_forward("Update is not a fast forward"));
+        assert!(!is_not_fast_forward("Reference does not exist"));
+        assert!(!is_not_fast_forward("Object does not exist"));
+        assert!(!is_not_fast_forward(""));
+    }
 }
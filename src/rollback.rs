@@ -0,0 +1,308 @@
+use node_semver::Version;
+
+use crate::{
+    channel::Channel,
+    channel_versioning_files, commit_to_registry, derived_files, get_channel_versioning,
+    get_registry_file, get_registry_policy, get_versioning, listed_versioning, manifest,
+    pull_request::Proposal,
+    requests::{RefUpdate, Requests, ResponseFile, COMMITS_PER_PAGE},
+    retry_when_moved,
+    target::Target,
+    utils::{config::Config, json::parse_base64_json},
+    versioning::{ContentRating, SourceChange, ADULT_VERSIONING_PATH},
+};
+
+// Reverts a source to an earlier registry version by committing the registry entry and the
+// files of that version on top of the current registry, the history itself is left untouched.
+// Without a target the version before the current one is restored, a target commit has to be
+// one of the commits which changed the versioning file of the channel.
+pub fn run(
+    request_client: &Requests,
+    config: &Config,
+    target: &Target,
+    id: &str,
+    to: Option<&str>,
+    channel: Channel,
+) -> Result<(), u8> {
+    retry_when_moved(|| rollback(request_client, config, target, id, to, channel))?;
+
+    println!("Rolled back {} in the {} channel", id, channel.name());
+
+    Ok(())
+}
+
+fn rollback(
+    request_client: &Requests,
+    config: &Config,
+    target: &Target,
+    id: &str,
+    to: Option<&str>,
+    channel: Channel,
+) -> Result<RefUpdate, u8> {
+    let registry_ref = request_client.get_ref(&target.repository, &target.branch)?;
+    let registry_commit =
+        request_client.get_commit(&target.repository, &registry_ref.object.sha)?;
+
+    let registry_tree =
+        request_client.get_tree(&target.repository, &registry_commit.tree.sha, true)?;
+
+    // The paths of the tree are relative to the channel directory
+    let current_tree = target.tree(channel, &registry_tree);
+
+    let registry_policy = get_registry_policy(request_client, target, &registry_tree)?;
+
+    let Some(mut registry_versioning) =
+        get_channel_versioning(request_client, target, &current_tree)?
+    else {
+        eprintln!(
+            "The {} channel does not exist in the registry",
+            channel.name()
+        );
+        return Err(0x1);
+    };
+
+    let Some(current_source) = registry_versioning.source(id) else {
+        eprintln!(
+            "The source {} does not exist in the {} channel of the registry",
+            id,
+            channel.name()
+        );
+        return Err(0x1);
+    };
+    let current_version = current_source.version().to_string();
+
+    // The history of an adult source is kept in the separate versioning file, when it exists
+    let versioning_path = match current_source.content_rating() == ContentRating::Adult
+        && current_tree
+            .tree
+            .iter()
+            .any(|file| file.path == ADULT_VERSIONING_PATH)
+    {
+        true => target.path(channel, ADULT_VERSIONING_PATH),
+        false => target.path(channel, "versioning.json"),
+    };
+
+    let target_version = to.filter(|to| to.parse::<Version>().is_ok());
+    let target_commit = to.filter(|to| to.parse::<Version>().is_err());
+
+    let mut restore_point = None;
+    let mut page = 1;
+
+    // The history is paged through until the restore point is found
+    'history: loop {
+        let history = request_client.get_commits(
+            &target.repository,
+            &versioning_path,
+            &registry_commit.sha,
+            page,
+        )?;
+
+        for commit in history.iter() {
+            if commit.sha == registry_commit.sha {
+                continue;
+            }
+
+            if target_commit.is_some_and(|target_commit| !commit.sha.starts_with(target_commit)) {
+                continue;
+            }
+
+            let versioning = get_versioning(
+                request_client,
+                &target.repository,
+                &versioning_path,
+                &commit.sha,
+            )?;
+
+            let Some(source) = versioning.source(id) else {
+                if target_commit.is_some() {
+                    eprintln!(
+                        "The registry commit {} does not contain the source {}",
+                        &commit.sha, id
+                    );
+                    return Err(0x1);
+                }
+
+                // The source was added to the registry after this commit
+                break 'history;
+            };
+
+            let is_target = match (target_version, target_commit) {
+                (Some(target_version), _) => source.version() == target_version,
+                (_, Some(_)) => true,
+                _ => source.version() != current_version,
+            };
+
+            if is_target {
+                restore_point = Some((commit.sha.clone(), source.clone()));
+                break 'history;
+            }
+        }
+
+        if history.len() < COMMITS_PER_PAGE {
+            break;
+        }
+
+        page += 1;
+    }
+
+    let Some((target_commit_sha, target_source)) = restore_point else {
+        eprintln!(
+            "No previous registry version of {} was found which matches: {}",
+            id,
+            to.unwrap_or("the version before the current one")
+        );
+        return Err(0x1);
+    };
+
+    println!(
+        "Rolling back {} from {} to {} of the registry commit {}",
+        id,
+        current_version,
+        target_source.version(),
+        &target_commit_sha
+    );
+
+    let target_tree = target.tree(
+        channel,
+        &request_client.get_tree(
+            &target.repository,
+            &request_client
+                .get_commit(&target.repository, &target_commit_sha)?
+                .tree
+                .sha,
+            true,
+        )?,
+    );
+
+    if current_tree.truncated || target_tree.truncated {
+        eprintln!("The registry tree was truncated by GitHub, the files of the source can not be restored");
+        return Err(0x1);
+    }
+
+    let prefix = format!("{}/", id);
+    let archive_prefix = format!("{}/versions/", id);
+
+    // The files of the target version are already stored in the registry, so their
+    // blobs are reused, files which were added afterwards are removed from the tree.
+    // The archive of the source is left untouched.
+    let is_source_file = |file: &&ResponseFile| {
+        file._type == "blob"
+            && file.path.starts_with(&prefix)
+            && !file.path.starts_with(&archive_prefix)
+    };
+
+    let mut blobs: Vec<(String, Option<String>)> = target_tree
+        .tree
+        .iter()
+        .filter(is_source_file)
+        .map(|file| (file.path.clone(), Some(file.sha.clone())))
+        .collect();
+
+    for file in current_tree.tree.iter().filter(is_source_file) {
+        if !blobs.iter().any(|(path, _)| *path == file.path) {
+            blobs.push((file.path.clone(), None));
+        }
+    }
+
+    let target_version = target_source.version().to_string();
+    let change = SourceChange::restored(&target_source, Some(&current_version));
+    // The changelog records the rollback for the repository the restored version was published from
+    let source_repository = target_source
+        .build()
+        .map(|build| build.repository().to_string())
+        .unwrap_or(target.repository.clone());
+
+    registry_versioning.restore_source(target_source);
+
+    let (mut files, removed_files) = channel_versioning_files(
+        &registry_versioning,
+        &registry_policy,
+        &current_tree,
+        &config.output_format,
+    )?;
+
+    let (derived_files, removed_derived_files) = derived_files(
+        request_client,
+        config,
+        target,
+        &current_tree,
+        &listed_versioning(&registry_versioning, &registry_policy),
+        &source_repository,
+        &[change],
+        None,
+    )?;
+
+    files.extend(derived_files);
+
+    for (path, content) in files {
+        let blob = request_client.create_blob(&target.repository, content)?;
+        blobs.push((path, Some(blob.sha)));
+    }
+
+    blobs.extend(
+        removed_files
+            .into_iter()
+            .chain(removed_derived_files)
+            .map(|path| (path, None)),
+    );
+
+    // The checksums of the restored files are taken from the manifest of the target commit
+    if config.manifest {
+        let target_manifest_source =
+            match get_registry_file(request_client, target, &target_tree, "manifest.json")? {
+                Some(content) => parse_base64_json::<manifest::Manifest>(&content, "manifest")?
+                    .source(id)
+                    .cloned(),
+                None => None,
+            };
+
+        if target_manifest_source.is_none() {
+            eprintln!(
+                "The manifest of the registry commit {} does not contain {}, its checksums are removed from the manifest",
+                &target_commit_sha, id
+            );
+        }
+
+        blobs.extend(manifest::restore_blobs(
+            request_client,
+            target,
+            &current_tree,
+            id,
+            target_manifest_source,
+            config.signing_key.as_ref(),
+        )?);
+    }
+
+    let blobs = blobs
+        .into_iter()
+        .map(|(path, sha)| (target.path(channel, &path), sha))
+        .collect();
+
+    let rollback_tree =
+        request_client.create_tree_from_blobs(&target.repository, current_tree.sha, blobs)?;
+
+    let title = format!(
+        "Roll back {} from {} to {}",
+        id, current_version, target_version
+    );
+    let description = format!(
+        "Restores the registry entry and the files of {} from {}.",
+        id, target_commit_sha
+    );
+
+    commit_to_registry(
+        request_client,
+        config,
+        target,
+        channel,
+        rollback_tree.sha,
+        registry_commit.sha,
+        format!("{}\n\n{}", title, description),
+        Proposal {
+            kind: "rollback",
+            subject: id.to_string(),
+            title,
+            body: description,
+        },
+    )
+}
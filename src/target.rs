@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    channel::Channel,
+    requests::{GetBaseTreeResponse, ResponseFile},
+    versioning::{ContentPolicy, MetadataPolicy},
+    PublishMode, REGISTRY_BRANCH, REGISTRY_REPOSITORY,
+};
+
+// A registry the sources are published to, every target is merged and committed
+// separately. The registry can be stored in a directory of the repository, and the
+// policies of the environment can be overridden per target.
+// The policies are also accepted in snake case, as used by the configuration file.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Target {
+    pub repository: String,
+    #[serde(default = "default_branch")]
+    pub branch: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    prefix: String,
+    #[serde(alias = "metadata_policy", skip_serializing_if = "Option::is_none")]
+    pub metadata_policy: Option<MetadataPolicy>,
+    #[serde(alias = "content_policy", skip_serializing_if = "Option::is_none")]
+    pub content_policy: Option<ContentPolicy>,
+    #[serde(alias = "publish_mode", skip_serializing_if = "Option::is_none")]
+    pub publish_mode: Option<PublishMode>,
+}
+
+fn default_branch() -> String {
+    REGISTRY_BRANCH.to_string()
+}
+
+impl Target {
+    // The label used in the output to tell the targets apart
+    pub fn name(&self) -> String {
+        match self.prefix.is_empty() {
+            true => format!("{}@{}", self.repository, self.branch),
+            false => format!("{}@{}:{}", self.repository, self.branch, self.prefix),
+        }
+    }
+
+    // Without configured targets the sources are published to the community registry
+    pub fn community_registry() -> Target {
+        Target {
+            repository: REGISTRY_REPOSITORY.to_string(),
+            branch: REGISTRY_BRANCH.to_string(),
+            prefix: String::new(),
+            metadata_policy: None,
+            content_policy: None,
+            publish_mode: None,
+        }
+    }
+
+    // The directory of the registry in the repository, empty or ending with a slash
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    // Returns the path in the registry repository of a path within a channel of the target
+    pub fn path(&self, channel: Channel, path: &str) -> String {
+        format!("{}{}", self.prefix, channel.path(path))
+    }
+
+    // Returns the part of a recursive registry tree which belongs to a channel of the
+    // target, with the paths relative to the channel directory
+    pub fn tree(
+        &self,
+        channel: Channel,
+        registry_tree: &GetBaseTreeResponse,
+    ) -> GetBaseTreeResponse {
+        let target_tree = GetBaseTreeResponse {
+            sha: registry_tree.sha.clone(),
+            url: registry_tree.url.clone(),
+            truncated: registry_tree.truncated,
+            tree: registry_tree
+                .tree
+                .iter()
+                .filter_map(|file| {
+                    let path = file.path.strip_prefix(&self.prefix)?;
+
+                    (!path.is_empty()).then(|| ResponseFile {
+                        path: path.to_string(),
+                        ..file.clone()
+                    })
+                })
+                .collect(),
+        };
+
+        channel.tree(&target_tree)
+    }
+}
+
+// Parses the JSON list of targets, as used by the environment and the command line
+pub fn parse_targets(value: &str) -> Result<Vec<Target>, String> {
+    validate_targets(serde_json::from_str(value).map_err(|err| err.to_string())?)
+}
+
+// Checks the targets, the prefixes are normalized to end with a slash
+pub fn validate_targets(mut targets: Vec<Target>) -> Result<Vec<Target>, String> {
+    if targets.is_empty() {
+        return Err("at least one target is required".to_string());
+    }
+
+    for target in targets.iter_mut() {
+        if target
+            .repository
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .count()
+            != 2
+        {
+            return Err(format!(
+                "the repository {} should be of the structure \"<owner>/<repository>\"",
+                target.repository
+            ));
+        }
+
+        let prefix = target.prefix.trim_matches('/');
+        target.prefix = match prefix.is_empty() {
+            true => String::new(),
+            false => format!("{}/", prefix),
+        };
+    }
+
+    for (index, target) in targets.iter().enumerate() {
+        if targets[..index]
+            .iter()
+            .any(|other| other.name() == target.name())
+        {
+            return Err(format!(
+                "the target {} is listed more than once",
+                target.name()
+            ));
+        }
+    }
+
+    Ok(targets)
+}
+
+// Selects the target with the given repository or name, all targets are selected without one
+pub fn select<'a>(targets: &'a [Target], name: Option<&str>) -> Result<Vec<&'a Target>, u8> {
+    let Some(name) = name else {
+        return Ok(targets.iter().collect());
+    };
+
+    let selected: Vec<&Target> = targets
+        .iter()
+        .filter(|target| target.repository == name || target.name() == name)
+        .collect();
+
+    if selected.is_empty() {
+        eprintln!("No registry target was found which matches: {}", name);
+        return Err(0x1);
+    }
+
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_default_to_the_registry_branch_and_normalize_their_prefix() {
+        let targets = parse_targets(
+            r#"[
+                {"repository": "org/registry"},
+                {"repository": "org/registry", "branch": "main", "prefix": "/ext/"},
+                {"repository": "org/mirror", "metadata_policy": "update", "publishMode": "pull-request"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(targets[0].branch, REGISTRY_BRANCH);
+        assert_eq!(targets[0].prefix(), "");
+        assert_eq!(targets[1].prefix(), "ext/");
+        assert_eq!(targets[1].name(), "org/registry@main:ext/");
+        assert_eq!(
+            targets[1].path(Channel::Beta, "versioning.json"),
+            "ext/beta/versioning.json"
+        );
+        assert!(matches!(
+            targets[2].metadata_policy,
+            Some(MetadataPolicy::Update)
+        ));
+        assert!(matches!(
+            targets[2].publish_mode,
+            Some(PublishMode::PullRequest)
+        ));
+    }
+
+    #[test]
+    fn invalid_targets_are_rejected() {
+        assert!(parse_targets("[]").is_err());
+        assert!(parse_targets(r#"[{"repository": "registry"}]"#).is_err());
+        assert!(parse_targets(r#"[{"repository": "org/registry", "unknown": true}]"#).is_err());
+        assert!(parse_targets(
+            r#"[{"repository": "org/registry", "prefix": "ext"}, {"repository": "org/registry", "prefix": "/ext"}]"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn targets_are_selected_by_repository_or_name() {
+        let targets = parse_targets(
+            r#"[{"repository": "org/registry"}, {"repository": "org/mirror", "prefix": "ext"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(select(&targets, None).unwrap().len(), 2);
+        assert_eq!(
+            select(&targets, Some("org/mirror@master:ext/")).unwrap()[0].repository,
+            "org/mirror"
+        );
+        assert!(select(&targets, Some("org/other")).is_err());
+    }
+}
@@ -1 +1,4 @@
+pub mod args;
+pub mod config;
 pub mod env;
+pub mod json;
@@ -0,0 +1,275 @@
+use std::env;
+
+use crate::channel::Channel;
+
+#[derive(Debug, Default)]
+pub enum Command {
+    #[default]
+    Publish,
+    Rollback {
+        id: String,
+        to: Option<String>,
+        channel: Channel,
+    },
+    Promote {
+        id: String,
+        from: Channel,
+        to: Channel,
+    },
+    Verify {
+        path: String,
+    },
+    ConfigShow,
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Command,
+    pub allow_downgrade: Vec<String>,
+    pub target: Option<String>,
+    pub config: Option<String>,
+    // Settings of the structure "<key>=<value>", these override the configuration
+    pub settings: Vec<String>,
+}
+
+pub fn parse() -> Result<Args, u8> {
+    parse_from(env::args().skip(1))
+}
+
+fn parse_from(mut raw_args: impl Iterator<Item = String>) -> Result<Args, u8> {
+    let mut args = Args::default();
+    let mut positionals = vec![];
+    let mut to = None;
+    let mut from = None;
+    let mut channel = None;
+
+    while let Some(raw_arg) = raw_args.next() {
+        let (flag, inline_value) = match raw_arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (raw_arg.clone(), None),
+        };
+
+        match flag.as_str() {
+            "--allow-downgrade" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => args.allow_downgrade.extend(
+                    value
+                        .split(',')
+                        .map(|id| id.trim().to_string())
+                        .filter(|id| !id.is_empty()),
+                ),
+                None => {
+                    eprintln!("The --allow-downgrade flag requires the id of the source which may be downgraded");
+                    return Err(0x1);
+                }
+            },
+            "--to" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => to = Some(value),
+                None => {
+                    eprintln!("The --to flag requires the registry version or commit to roll back to, or the channel to promote to");
+                    return Err(0x1);
+                }
+            },
+            "--target" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => args.target = Some(value),
+                None => {
+                    eprintln!("The --target flag requires the repository of the registry target");
+                    return Err(0x1);
+                }
+            },
+            "--config" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => args.config = Some(value),
+                None => {
+                    eprintln!("The --config flag requires the path of the configuration file");
+                    return Err(0x1);
+                }
+            },
+            "--set" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => args.settings.push(value),
+                None => {
+                    eprintln!(
+                        "The --set flag requires a setting of the structure \"<key>=<value>\""
+                    );
+                    return Err(0x1);
+                }
+            },
+            "--from" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => from = Some(value),
+                None => {
+                    eprintln!("The --from flag requires the channel to promote from");
+                    return Err(0x1);
+                }
+            },
+            "--channel" => match inline_value.or_else(|| raw_args.next()) {
+                Some(value) => channel = Some(value),
+                None => {
+                    eprintln!("The --channel flag requires the channel to roll back");
+                    return Err(0x1);
+                }
+            },
+            _ if flag.starts_with("--") => {
+                eprintln!("The provided argument is unknown: {}", &raw_arg);
+                return Err(0x1);
+            }
+            _ => positionals.push(raw_arg),
+        }
+    }
+
+    args.command = match positionals
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] | ["publish"] => Command::Publish,
+        ["rollback", id] => Command::Rollback {
+            id: id.to_string(),
+            to: to.take(),
+            channel: parse_channel("--channel", channel.take().as_deref().unwrap_or("stable"))?,
+        },
+        ["promote", id] => match (from.take(), to.take()) {
+            (Some(from), Some(to)) => Command::Promote {
+                id: id.to_string(),
+                from: parse_channel("--from", &from)?,
+                to: parse_channel("--to", &to)?,
+            },
+            _ => {
+                eprintln!("The promote command requires the channels to promote between, e.g. \"promote <source-id> --from beta --to stable\"");
+                return Err(0x1);
+            }
+        },
+        ["promote"] => {
+            eprintln!("The promote command requires the id of the source to promote, e.g. \"promote <source-id> --from beta --to stable\"");
+            return Err(0x1);
+        }
+        ["verify"] => Command::Verify {
+            path: ".".to_string(),
+        },
+        ["verify", path] => Command::Verify {
+            path: path.to_string(),
+        },
+        ["config", "show"] => Command::ConfigShow,
+        ["rollback"] => {
+            eprintln!("The rollback command requires the id of the source to roll back, e.g. \"rollback <source-id> [--to <version|commit>]\"");
+            return Err(0x1);
+        }
+        _ => {
+            eprintln!("The provided command is unknown: {}", positionals.join(" "));
+            return Err(0x1);
+        }
+    };
+
+    if to.is_some() {
+        eprintln!("The --to flag can only be used with the rollback and promote commands");
+        return Err(0x1);
+    }
+
+    if from.is_some() {
+        eprintln!("The --from flag can only be used with the promote command");
+        return Err(0x1);
+    }
+
+    if channel.is_some() {
+        eprintln!("The --channel flag can only be used with the rollback command");
+        return Err(0x1);
+    }
+
+    println!("Parsed the command line arguments");
+
+    Ok(args)
+}
+
+fn parse_channel(flag: &str, value: &str) -> Result<Channel, u8> {
+    match Channel::from_name(value) {
+        Some(channel) => Ok(channel),
+        None => {
+            eprintln!(
+                "The provided channel of the {} flag is invalid, it should be either \"stable\", \"beta\" or \"nightly\": {}",
+                flag, value
+            );
+            Err(0x1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(raw_args: &[&str]) -> Result<Args, u8> {
+        parse_from(raw_args.iter().map(|raw_arg| raw_arg.to_string()))
+    }
+
+    #[test]
+    fn publish_is_the_default_command() {
+        assert!(matches!(parse_args(&[]).unwrap().command, Command::Publish));
+        assert!(matches!(
+            parse_args(&["publish"]).unwrap().command,
+            Command::Publish
+        ));
+    }
+
+    #[test]
+    fn flags_accept_the_value_inline_or_as_the_next_argument() {
+        let args = parse_args(&[
+            "--allow-downgrade=A, B",
+            "--allow-downgrade",
+            "C",
+            "--target",
+            "org/registry",
+            "--config=registry.toml",
+            "--set",
+            "output.html=true",
+            "--set=publish.commit_template=a=b",
+        ])
+        .unwrap();
+
+        assert_eq!(args.allow_downgrade, vec!["A", "B", "C"]);
+        assert_eq!(args.target.as_deref(), Some("org/registry"));
+        assert_eq!(args.config.as_deref(), Some("registry.toml"));
+        assert_eq!(
+            args.settings,
+            vec!["output.html=true", "publish.commit_template=a=b"]
+        );
+    }
+
+    #[test]
+    fn commands_are_parsed_with_their_flags() {
+        assert!(matches!(
+            parse_args(&["rollback", "Example"]).unwrap().command,
+            Command::Rollback { id, to: None, channel: Channel::Stable } if id == "Example"
+        ));
+        assert!(matches!(
+            parse_args(&["rollback", "Example", "--to", "1.0.0", "--channel", "beta"]).unwrap().command,
+            Command::Rollback { to: Some(to), channel: Channel::Beta, .. } if to == "1.0.0"
+        ));
+        assert!(matches!(
+            parse_args(&["promote", "Example", "--from", "beta", "--to", "stable"])
+                .unwrap()
+                .command,
+            Command::Promote {
+                from: Channel::Beta,
+                to: Channel::Stable,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse_args(&["verify"]).unwrap().command,
+            Command::Verify { path } if path == "."
+        ));
+        assert!(matches!(
+            parse_args(&["config", "show"]).unwrap().command,
+            Command::ConfigShow
+        ));
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse_args(&["unknown"]).is_err());
+        assert!(parse_args(&["--unknown"]).is_err());
+        assert!(parse_args(&["--target"]).is_err());
+        assert!(parse_args(&["promote", "Example", "--from", "beta"]).is_err());
+        assert!(parse_args(&["rollback", "Example", "--channel", "alpha"]).is_err());
+        assert!(parse_args(&["publish", "--to", "1.0.0"]).is_err());
+        assert!(parse_args(&["rollback", "Example", "--from", "beta"]).is_err());
+    }
+}
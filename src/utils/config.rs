@@ -0,0 +1,712 @@
+use std::{env, fs, path::Path, str::FromStr};
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use node_semver::Range;
+use serde::{
+    de::{value::StrDeserializer, DeserializeOwned, IntoDeserializer},
+    Deserialize, Serialize,
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    changelog::ChangelogFormat,
+    channel::Channel,
+    manifest,
+    target::{self, Target},
+    utils::args::{Args, Command},
+    validation::Limits,
+    versioning::{self, ContentPolicy, MetadataPolicy, OutputFormat, SourceOrder, TypesPrerelease},
+    PublishMode,
+};
+
+const DEFAULT_PATH: &str = "registry-manager.toml";
+
+// Every setting by its key in the configuration file and its environment variable
+const SETTINGS: [(&str, &str); 24] = [
+    ("pat", "REGISTRY_MANAGER_PAT"),
+    ("repository", "REGISTRY_MANAGER_REPOSITORY"),
+    ("branch", "REGISTRY_MANAGER_BRANCH"),
+    ("targets", "REGISTRY_MANAGER_TARGETS"),
+    ("publish.mode", "REGISTRY_MANAGER_PUBLISH_MODE"),
+    (
+        "publish.commit_template",
+        "REGISTRY_MANAGER_COMMIT_TEMPLATE",
+    ),
+    ("policy.metadata", "REGISTRY_MANAGER_METADATA_POLICY"),
+    ("policy.content", "REGISTRY_MANAGER_CONTENT_POLICY"),
+    ("policy.types_range", "REGISTRY_MANAGER_TYPES_RANGE"),
+    (
+        "policy.types_prerelease",
+        "REGISTRY_MANAGER_TYPES_PRERELEASE",
+    ),
+    ("versioning.indent", "REGISTRY_MANAGER_VERSIONING_INDENT"),
+    ("versioning.order", "REGISTRY_MANAGER_VERSIONING_ORDER"),
+    ("output.changelog", "REGISTRY_MANAGER_CHANGELOG"),
+    ("output.index", "REGISTRY_MANAGER_INDEX"),
+    ("output.html", "REGISTRY_MANAGER_HTML"),
+    ("output.manifest", "REGISTRY_MANAGER_MANIFEST"),
+    (
+        "output.archive_retention",
+        "REGISTRY_MANAGER_ARCHIVE_RETENTION",
+    ),
+    ("signing.signing_key", "REGISTRY_MANAGER_SIGNING_KEY"),
+    ("signing.public_key", "REGISTRY_MANAGER_PUBLIC_KEY"),
+    ("limits.icon_max_size", "REGISTRY_MANAGER_ICON_MAX_SIZE"),
+    (
+        "limits.icon_max_dimension",
+        "REGISTRY_MANAGER_ICON_MAX_DIMENSION",
+    ),
+    ("limits.static_max_size", "REGISTRY_MANAGER_STATIC_MAX_SIZE"),
+    ("limits.bundle_max_size", "REGISTRY_MANAGER_BUNDLE_MAX_SIZE"),
+    ("limits.bundle_denylist", "REGISTRY_MANAGER_BUNDLE_DENYLIST"),
+];
+
+// The values of these settings are never printed
+const SECRETS: [&str; 2] = ["pat", "signing.signing_key"];
+
+// The settings of a single source, the defaults, the configuration file, the environment
+// or the command line. The layout is the one of the configuration file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct Layer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pat: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    targets: Option<Vec<Target>>,
+    publish: PublishLayer,
+    policy: PolicyLayer,
+    versioning: VersioningLayer,
+    output: OutputLayer,
+    signing: SigningLayer,
+    limits: LimitsLayer,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct PublishLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<PublishMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit_template: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<MetadataPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<ContentPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    types_range: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    types_prerelease: Option<TypesPrerelease>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct VersioningLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    indent: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<SourceOrder>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct OutputLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changelog: Option<ChangelogFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive_retention: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct SigningLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signing_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public_key: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct LimitsLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_max_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_max_dimension: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    static_max_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bundle_max_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bundle_denylist: Option<Vec<String>>,
+}
+
+// The policies and formats are written the same way as in the configuration file
+fn variant<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    let deserializer: StrDeserializer<serde::de::value::Error> = value.into_deserializer();
+    T::deserialize(deserializer).map_err(|err| err.to_string())
+}
+
+fn number<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| "it should be a positive number".to_string())
+}
+
+fn boolean(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err("it should be either \"true\" or \"false\"".to_string()),
+    }
+}
+
+impl Layer {
+    // The default of every setting which has one, these are the lowest layer
+    fn defaults() -> Layer {
+        Layer {
+            targets: Some(vec![Target::community_registry()]),
+            publish: PublishLayer {
+                mode: Some(PublishMode::Commit),
+                commit_template: None,
+            },
+            policy: PolicyLayer {
+                metadata: Some(MetadataPolicy::Warn),
+                content: Some(ContentPolicy::Fail),
+                types_range: None,
+                types_prerelease: Some(TypesPrerelease::Allow),
+            },
+            versioning: VersioningLayer {
+                indent: Some(2),
+                order: Some(SourceOrder::Id),
+            },
+            output: OutputLayer {
+                changelog: Some(ChangelogFormat::None),
+                index: Some(false),
+                html: Some(false),
+                manifest: Some(false),
+                archive_retention: Some(0),
+            },
+            limits: LimitsLayer {
+                icon_max_size: Some(512 * 1024),
+                icon_max_dimension: Some(1024),
+                static_max_size: Some(5 * 1024 * 1024),
+                bundle_max_size: Some(5 * 1024 * 1024),
+                bundle_denylist: Some(vec![]),
+            },
+            ..Layer::default()
+        }
+    }
+
+    // Sets a setting from its text form, as used by the environment variables and --set.
+    // The targets are a JSON list and the denylist patterns are separated by a semicolon.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let text = || Some(value.to_string());
+
+        match key {
+            "pat" => self.pat = text(),
+            "repository" => self.repository = text(),
+            "branch" => self.branch = text(),
+            "targets" => self.targets = Some(target::parse_targets(value)?),
+            "publish.mode" => self.publish.mode = Some(variant(value)?),
+            "publish.commit_template" => self.publish.commit_template = text(),
+            "policy.metadata" => self.policy.metadata = Some(variant(value)?),
+            "policy.content" => self.policy.content = Some(variant(value)?),
+            "policy.types_range" => self.policy.types_range = text(),
+            "policy.types_prerelease" => self.policy.types_prerelease = Some(variant(value)?),
+            "versioning.indent" => self.versioning.indent = Some(number(value)?),
+            "versioning.order" => self.versioning.order = Some(variant(value)?),
+            "output.changelog" => self.output.changelog = Some(variant(value)?),
+            "output.index" => self.output.index = Some(boolean(value)?),
+            "output.html" => self.output.html = Some(boolean(value)?),
+            "output.manifest" => self.output.manifest = Some(boolean(value)?),
+            "output.archive_retention" => self.output.archive_retention = Some(number(value)?),
+            "signing.signing_key" => self.signing.signing_key = text(),
+            "signing.public_key" => self.signing.public_key = text(),
+            "limits.icon_max_size" => self.limits.icon_max_size = Some(number(value)?),
+            "limits.icon_max_dimension" => self.limits.icon_max_dimension = Some(number(value)?),
+            "limits.static_max_size" => self.limits.static_max_size = Some(number(value)?),
+            "limits.bundle_max_size" => self.limits.bundle_max_size = Some(number(value)?),
+            "limits.bundle_denylist" => {
+                self.limits.bundle_denylist = Some(
+                    value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|pattern| !pattern.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                )
+            }
+            _ => return Err(format!("the setting {} is unknown", key)),
+        }
+
+        Ok(())
+    }
+
+    fn to_object(&self) -> Map<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(object)) => object,
+            _ => Map::new(),
+        }
+    }
+}
+
+// Overrides the values of the lower layer by the ones of the upper layer
+fn merge(lower: &mut Map<String, Value>, upper: &Map<String, Value>) {
+    for (key, value) in upper.iter() {
+        match (lower.get_mut(key), value) {
+            (Some(Value::Object(lower)), Value::Object(upper)) => merge(lower, upper),
+            _ => {
+                lower.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn lookup<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    match key.split_once('.') {
+        Some((section, key)) => object.get(section)?.as_object()?.get(key),
+        None => object.get(key),
+    }
+}
+
+// The configuration file is read from the path of the --config flag or REGISTRY_MANAGER_CONFIG,
+// the default file is optional
+fn read_file(path: Option<&str>) -> Result<Layer, u8> {
+    let (path, explicit) = match path
+        .map(str::to_string)
+        .or_else(|| env::var("REGISTRY_MANAGER_CONFIG").ok())
+    {
+        Some(path) => (path, true),
+        None => (DEFAULT_PATH.to_string(), false),
+    };
+
+    if !explicit && !Path::new(&path).exists() {
+        return Ok(Layer::default());
+    }
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!(
+                "An error occurred while reading the configuration file {}: {}",
+                &path, &err
+            );
+            return Err(0x1);
+        }
+    };
+
+    match toml::from_str(&content) {
+        Ok(layer) => {
+            println!("Loaded the configuration file {}", &path);
+            Ok(layer)
+        }
+        Err(err) => {
+            eprintln!(
+                "An error occurred while parsing the configuration file {}: {}",
+                &path, &err
+            );
+            Err(0x1)
+        }
+    }
+}
+
+// The effective configuration of the run
+#[derive(Debug)]
+pub struct Config {
+    pub pat: Option<String>,
+    pub repository: Option<String>,
+    pub channel: Channel,
+    pub targets: Vec<Target>,
+    pub publish_mode: PublishMode,
+    pub commit_template: Option<String>,
+    pub metadata_policy: MetadataPolicy,
+    pub content_policy: ContentPolicy,
+    pub types_ranges: Vec<(Option<String>, Range)>,
+    pub types_prerelease: TypesPrerelease,
+    pub output_format: OutputFormat,
+    pub changelog: ChangelogFormat,
+    pub index: bool,
+    pub html: bool,
+    pub manifest: bool,
+    // The archive is disabled when the retention is 0
+    pub archive_retention: Option<usize>,
+    pub signing_key: Option<SigningKey>,
+    pub public_key: Option<VerifyingKey>,
+    pub limits: Limits,
+    // Every layer from the lowest to the highest, kept to show where the values come from
+    layers: Vec<(&'static str, Map<String, Value>)>,
+}
+
+// Builds the effective configuration from the defaults, the configuration file, the
+// environment variables and the --set flags, every layer overrides the previous ones.
+// Every value is validated here, the rest of the program only uses the typed values.
+pub fn load(args: &Args) -> Result<Config, u8> {
+    let file = read_file(args.config.as_deref())?;
+
+    let mut environment = Layer::default();
+
+    for (key, name) in SETTINGS {
+        if let Ok(value) = env::var(name) {
+            if let Err(err) = environment.set(key, &value) {
+                eprintln!("The provided value of {} is invalid, {}", name, err);
+                return Err(0x1);
+            }
+        }
+    }
+
+    let mut command_line = Layer::default();
+
+    for setting in args.settings.iter() {
+        let Some((key, value)) = setting.split_once('=') else {
+            eprintln!(
+                "The --set flag requires a setting of the structure \"<key>=<value>\": {}",
+                setting
+            );
+            return Err(0x1);
+        };
+
+        if let Err(err) = command_line.set(key.trim(), value) {
+            eprintln!(
+                "The provided value of {} of the --set flag is invalid, {}",
+                key.trim(),
+                err
+            );
+            return Err(0x1);
+        }
+    }
+
+    build(
+        vec![
+            ("default", Layer::defaults()),
+            ("configuration file", file),
+            ("environment", environment),
+            ("command line", command_line),
+        ],
+        &args.command,
+    )
+}
+
+// Merges the layers from the lowest to the highest and validates the result
+fn build(layers: Vec<(&'static str, Layer)>, command: &Command) -> Result<Config, u8> {
+    let layers: Vec<(&'static str, Map<String, Value>)> = layers
+        .iter()
+        .map(|(source, layer)| (*source, layer.to_object()))
+        .collect();
+
+    let mut merged = Map::new();
+
+    for (_, object) in layers.iter() {
+        merge(&mut merged, object);
+    }
+
+    let layer: Layer = match serde_json::from_value(Value::Object(merged)) {
+        Ok(layer) => layer,
+        Err(err) => {
+            eprintln!(
+                "An error occurred while merging the configuration: {}",
+                &err
+            );
+            return Err(0x1);
+        }
+    };
+
+    resolve(layer, command, layers)
+}
+
+// Settings with a default are always set, as the defaults are the lowest layer
+fn resolve(
+    layer: Layer,
+    command: &Command,
+    layers: Vec<(&'static str, Map<String, Value>)>,
+) -> Result<Config, u8> {
+    // Verifying a registry checkout and showing the configuration do not request anything
+    let requires_pat = !matches!(command, Command::Verify { .. } | Command::ConfigShow);
+    let requires_repository = matches!(command, Command::Publish);
+
+    match &layer.pat {
+        Some(pat) if !pat.starts_with("github_pat_") || pat.len() != 93 => {
+            eprintln!("The provided personal_access_token is invalid, for more info check https://github.blog/security/application-security/introducing-fine-grained-personal-access-tokens-for-github/");
+            return Err(0x1);
+        }
+        None if requires_pat => {
+            eprintln!("The personal access token was not found, set REGISTRY_MANAGER_PAT or pat in the configuration file");
+            return Err(0x1);
+        }
+        _ => (),
+    }
+
+    match &layer.repository {
+        Some(repository)
+            if !repository.starts_with("paperback-community/") || repository.len() < 20 =>
+        {
+            eprintln!("The provided repository is invalid, it should be of the structure \"paperback-community/<repository_name>\", consider using \"$${{ github.repository_name }}\"");
+            return Err(0x1);
+        }
+        None if requires_repository => {
+            eprintln!("The extension repository was not found, set REGISTRY_MANAGER_REPOSITORY or repository in the configuration file");
+            return Err(0x1);
+        }
+        _ => (),
+    }
+
+    let channel = match &layer.branch {
+        Some(branch) => match Channel::from_branch(branch) {
+            Some(channel)
+                if !branch
+                    .split_once('/')
+                    .is_some_and(|(_, version)| version.is_empty()) =>
+            {
+                channel
+            }
+            _ => {
+                eprintln!("The provided branch is invalid, it should be of the structure \"<stable|beta|nightly>/<paperback_semver>\", consider using \"$${{ github.ref_name }}\"");
+                return Err(0x1);
+            }
+        },
+        None if requires_repository => {
+            eprintln!("The branch of the extension repository was not found, set REGISTRY_MANAGER_BRANCH or branch in the configuration file");
+            return Err(0x1);
+        }
+        None => Channel::Stable,
+    };
+
+    let targets = match target::validate_targets(layer.targets.unwrap()) {
+        Ok(targets) => targets,
+        Err(err) => {
+            eprintln!("The provided registry targets are invalid, they should be a list of objects with a \"repository\" and optionally a \"branch\", \"prefix\", \"metadataPolicy\", \"contentPolicy\" and \"publishMode\": {}", err);
+            return Err(0x1);
+        }
+    };
+
+    let types_ranges = match layer.policy.types_range.as_deref() {
+        Some(value) => match versioning::parse_types_ranges(value) {
+            Ok(types_ranges) => types_ranges,
+            Err(err) => {
+                eprintln!("The provided @paperback/types range is invalid, it should be a semver range optionally prefixed by the registry branch it applies to, e.g. \"master=^0.9.0-alpha\", multiple ranges are separated by a semicolon: {}", err);
+                return Err(0x1);
+            }
+        },
+        None => vec![],
+    };
+
+    let signing_key = match layer.signing.signing_key.as_deref() {
+        Some(value) => match manifest::decode_signing_key(value) {
+            Some(signing_key) => Some(signing_key),
+            None => {
+                eprintln!("The provided signing key is invalid, it should be a base64 encoded ed25519 private key seed of 32 bytes");
+                return Err(0x1);
+            }
+        },
+        None => None,
+    };
+
+    let public_key = match layer.signing.public_key.as_deref() {
+        Some(value) => match manifest::decode_verifying_key(value) {
+            Some(public_key) => Some(public_key),
+            None => {
+                eprintln!("The provided public key is invalid, it should be a base64 encoded ed25519 public key of 32 bytes");
+                return Err(0x1);
+            }
+        },
+        None => None,
+    };
+
+    println!("Validated the configuration");
+
+    Ok(Config {
+        pat: layer.pat,
+        repository: layer.repository,
+        channel,
+        targets,
+        publish_mode: layer.publish.mode.unwrap(),
+        commit_template: layer.publish.commit_template,
+        metadata_policy: layer.policy.metadata.unwrap(),
+        content_policy: layer.policy.content.unwrap(),
+        types_ranges,
+        types_prerelease: layer.policy.types_prerelease.unwrap(),
+        output_format: OutputFormat {
+            indent: layer.versioning.indent.unwrap(),
+            order: layer.versioning.order.unwrap(),
+        },
+        changelog: layer.output.changelog.unwrap(),
+        index: layer.output.index.unwrap(),
+        html: layer.output.html.unwrap(),
+        manifest: layer.output.manifest.unwrap(),
+        archive_retention: layer
+            .output
+            .archive_retention
+            .filter(|retention| *retention > 0),
+        signing_key,
+        public_key,
+        limits: Limits {
+            icon_max_size: layer.limits.icon_max_size.unwrap(),
+            icon_max_dimension: layer.limits.icon_max_dimension.unwrap(),
+            static_max_size: layer.limits.static_max_size.unwrap(),
+            bundle_max_size: layer.limits.bundle_max_size.unwrap(),
+            bundle_denylist: layer.limits.bundle_denylist.unwrap(),
+        },
+        layers,
+    })
+}
+
+impl Config {
+    // The repository is required when publishing, which is checked while loading
+    pub fn repository(&self) -> &str {
+        self.repository.as_deref().unwrap_or_default()
+    }
+
+    // Prints the effective configuration and the layer every value comes from
+    pub fn show(&self) {
+        println!("Effective configuration:");
+
+        for (key, _) in SETTINGS {
+            let value = self
+                .layers
+                .iter()
+                .rev()
+                .find_map(|(source, object)| lookup(object, key).map(|value| (source, value)));
+
+            match value {
+                Some((source, _)) if SECRETS.contains(&key) => {
+                    println!("{} = <redacted> ({})", key, source)
+                }
+                Some((source, value)) => println!("{} = {} ({})", key, value, source),
+                None => println!("{} is not set", key),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(settings: &[(&str, &str)]) -> Layer {
+        let mut layer = Layer::default();
+
+        for (key, value) in settings {
+            layer.set(key, value).unwrap();
+        }
+
+        layer
+    }
+
+    #[test]
+    fn higher_layers_override_the_lower_ones_per_setting() {
+        let file: Layer = toml::from_str(
+            "[output]\nindex = true\narchive_retention = 5\n\n[versioning]\nindent = 4\n",
+        )
+        .unwrap();
+
+        let config = build(
+            vec![
+                ("default", Layer::defaults()),
+                ("configuration file", file),
+                ("environment", layer(&[("output.archive_retention", "3")])),
+                ("command line", layer(&[("versioning.indent", "0")])),
+            ],
+            &Command::ConfigShow,
+        )
+        .unwrap();
+
+        assert!(config.index);
+        assert!(!config.html);
+        assert_eq!(config.archive_retention, Some(3));
+        assert_eq!(config.output_format.indent, 0);
+        assert!(matches!(config.output_format.order, SourceOrder::Id));
+    }
+
+    #[test]
+    fn the_outputs_are_disabled_by_default() {
+        let config = build(vec![("default", Layer::defaults())], &Command::ConfigShow).unwrap();
+
+        assert!(matches!(config.changelog, ChangelogFormat::None));
+        assert!(!config.index);
+        assert!(!config.html);
+        assert!(!config.manifest);
+        assert_eq!(config.archive_retention, None);
+    }
+
+    #[test]
+    fn unknown_settings_and_invalid_values_are_rejected() {
+        let mut layer = Layer::default();
+
+        assert_eq!(
+            layer.set("output.unknown", "true"),
+            Err("the setting output.unknown is unknown".to_string())
+        );
+        assert!(layer.set("output.index", "yes").is_err());
+        assert!(layer.set("versioning.indent", "-1").is_err());
+        assert!(layer.set("policy.metadata", "ignore").is_err());
+        assert!(toml::from_str::<Layer>("[output]\nunknown = true\n").is_err());
+    }
+
+    #[test]
+    fn values_are_validated_after_merging() {
+        let build_with = |settings: &[(&str, &str)], command: &Command| {
+            build(
+                vec![
+                    ("default", Layer::defaults()),
+                    ("environment", layer(settings)),
+                ],
+                command,
+            )
+        };
+
+        assert!(build_with(
+            &[("policy.types_range", "not a range")],
+            &Command::ConfigShow
+        )
+        .is_err());
+        assert!(build_with(&[("signing.public_key", "invalid")], &Command::ConfigShow).is_err());
+        assert!(build_with(&[("branch", "beta/")], &Command::ConfigShow).is_err());
+        // Publishing requires a personal access token
+        assert!(build_with(&[], &Command::Publish).is_err());
+    }
+
+    #[test]
+    fn merging_overrides_only_the_set_values_of_a_section() {
+        let mut lower = layer(&[("output.index", "true"), ("output.html", "true")]).to_object();
+        let upper = layer(&[("output.html", "false"), ("versioning.indent", "4")]).to_object();
+
+        merge(&mut lower, &upper);
+
+        assert_eq!(lookup(&lower, "output.index"), Some(&Value::Bool(true)));
+        assert_eq!(lookup(&lower, "output.html"), Some(&Value::Bool(false)));
+        assert_eq!(lookup(&lower, "versioning.indent"), Some(&Value::from(4)));
+        assert_eq!(lookup(&lower, "versioning.order"), None);
+    }
+
+    #[test]
+    fn the_configuration_file_is_read_from_the_given_path() {
+        let path = env::temp_dir().join("registry-manager-config-test.toml");
+        fs::write(
+            &path,
+            "repository = \"org/extensions\"\n\n[output]\nhtml = true\n",
+        )
+        .unwrap();
+
+        let layer = read_file(path.to_str()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(layer.repository.as_deref(), Some("org/extensions"));
+        assert_eq!(layer.output.html, Some(true));
+        // An explicitly given file has to exist
+        assert!(read_file(path.to_str()).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use base64::prelude::*;
+use serde::de::DeserializeOwned;
+
+// Decodes a base64 encoded JSON file of the GitHub API, the name of
+// the file is only used in the output
+pub fn parse_base64_json<T: DeserializeOwned>(response_content: &str, name: &str) -> Result<T, u8> {
+    match BASE64_STANDARD.decode(response_content.replace("\n", "")) {
+        Ok(bytes) => match serde_json::from_slice(bytes.as_slice()) {
+            Ok(value) => {
+                println!("Parsed the requested {} file", name);
+                Ok(value)
+            }
+            Err(err) => {
+                eprintln!(
+                    "An error occurred while deserializing the {} file to JSON: {}",
+                    name, &err
+                );
+                Err(0x1)
+            }
+        },
+        Err(err) => {
+            eprintln!(
+                "An error occurred while base64 decoding the {} file: {}",
+                name, &err
+            );
+            Err(0x1)
+        }
+    }
+}
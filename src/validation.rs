@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+
+use base64::prelude::*;
+
+use crate::{
+    registry_policy::RegistryPolicy,
+    versioning::{Source, UpdatedExtensions, Versioning},
+};
+
+// The sizes are in bytes, the denylist contains plain text patterns, e.g. "new Function("
+#[derive(Clone, Debug)]
+pub struct Limits {
+    pub icon_max_size: usize,
+    pub icon_max_dimension: u32,
+    pub static_max_size: usize,
+    pub bundle_max_size: usize,
+    pub bundle_denylist: Vec<String>,
+}
+
+impl Limits {
+    // The limits of the registry policy apply when they are stricter
+    pub fn restrict(&mut self, registry_policy: &RegistryPolicy) {
+        if let Some(max_icon_size) = registry_policy.max_icon_size() {
+            self.icon_max_size = self.icon_max_size.min(max_icon_size);
+        }
+
+        if let Some(max_static_size) = registry_policy.max_static_size() {
+            self.static_max_size = self.static_max_size.min(max_static_size);
+        }
+
+        if let Some(max_bundle_size) = registry_policy.max_bundle_size() {
+            self.bundle_max_size = self.bundle_max_size.min(max_bundle_size);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ImageFormat {
+    Png,
+    Jpeg,
+    Svg,
+}
+
+fn decode(content: &str) -> Option<Vec<u8>> {
+    BASE64_STANDARD.decode(content.replace("\n", "")).ok()
+}
+
+fn image_format(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(ImageFormat::Png);
+    }
+
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ImageFormat::Jpeg);
+    }
+
+    let text = std::str::from_utf8(bytes).ok()?;
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    if (text.starts_with("<?xml") || text.starts_with("<svg") || text.starts_with("<!--"))
+        && text.contains("<svg")
+    {
+        return Some(ImageFormat::Svg);
+    }
+
+    None
+}
+
+// Svg images are scalable and therefore have no fixed dimensions
+fn image_dimensions(format: &ImageFormat, bytes: &[u8]) -> Option<(u32, u32)> {
+    match format {
+        ImageFormat::Png => {
+            let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+            let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+            Some((width, height))
+        }
+        ImageFormat::Jpeg => {
+            let mut index = 2;
+
+            while index + 9 < bytes.len() {
+                if bytes[index] != 0xFF {
+                    return None;
+                }
+
+                let marker = bytes[index + 1];
+                let length = u16::from_be_bytes([bytes[index + 2], bytes[index + 3]]) as usize;
+
+                // The start of frame markers, excluding the huffman and arithmetic coding markers
+                if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                    let height = u16::from_be_bytes([bytes[index + 5], bytes[index + 6]]) as u32;
+                    let width = u16::from_be_bytes([bytes[index + 7], bytes[index + 8]]) as u32;
+                    return Some((width, height));
+                }
+
+                index += 2 + length;
+            }
+
+            None
+        }
+        ImageFormat::Svg => None,
+    }
+}
+
+fn validate_static_files(
+    source: &Source,
+    files: &HashMap<String, String>,
+    limits: &Limits,
+) -> Vec<String> {
+    let mut problems = vec![];
+
+    let static_prefix = format!("{}/static/", source.id());
+    let mut static_size = 0;
+
+    for (path, content) in files.iter() {
+        if !path.starts_with(&static_prefix) {
+            continue;
+        }
+
+        match decode(content) {
+            Some(bytes) => static_size += bytes.len(),
+            None => problems.push(format!("the static file {} could not be decoded", path)),
+        }
+    }
+
+    if static_size > limits.static_max_size {
+        problems.push(format!(
+            "the static files are {} bytes in total, the limit is {} bytes",
+            static_size, limits.static_max_size
+        ));
+    }
+
+    let icon_path = format!("{}{}", static_prefix, source.icon());
+
+    let Some(icon) = files.get(&icon_path).and_then(|content| decode(content)) else {
+        problems.push(format!("the icon {} does not exist", icon_path));
+        return problems;
+    };
+
+    if icon.len() > limits.icon_max_size {
+        problems.push(format!(
+            "the icon is {} bytes, the limit is {} bytes",
+            icon.len(),
+            limits.icon_max_size
+        ));
+    }
+
+    match image_format(&icon) {
+        Some(format) => {
+            if format != ImageFormat::Svg {
+                match image_dimensions(&format, &icon) {
+                    Some((width, height))
+                        if width > limits.icon_max_dimension
+                            || height > limits.icon_max_dimension =>
+                    {
+                        problems.push(format!(
+                            "the icon is {}x{} pixels, the limit is {}x{} pixels",
+                            width, height, limits.icon_max_dimension, limits.icon_max_dimension
+                        ))
+                    }
+                    Some(_) => (),
+                    None => {
+                        problems.push("the dimensions of the icon could not be read".to_string())
+                    }
+                }
+            }
+        }
+        None => problems.push(format!(
+            "the icon {} is not a PNG, JPEG or SVG image",
+            icon_path
+        )),
+    }
+
+    problems
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// The bundles created by the toolchain export the source under its id, depending on the
+// format as "exports.<id> =", "export { <id> }", "export const <id>" or as the getter
+// "<id>: () =>" of the esbuild export helper
+fn exports_source(bundle: &str, id: &str) -> bool {
+    let in_export_list = bundle.match_indices("export").any(|(index, _)| {
+        bundle[index + "export".len()..]
+            .trim_start()
+            .strip_prefix('{')
+            .and_then(|list| list.split_once('}'))
+            .is_some_and(|(list, _)| {
+                // The exported name is the alias when there is one, e.g. "Source as <id>"
+                list.split(',')
+                    .any(|item| item.split_whitespace().last() == Some(id))
+            })
+    });
+
+    in_export_list
+        || bundle.match_indices(id).any(|(index, _)| {
+            let before = &bundle[..index];
+            let after = &bundle[index + id.len()..];
+
+            if before.ends_with(is_identifier_char) || after.starts_with(is_identifier_char) {
+                return false;
+            }
+
+            let declared = ["const", "let", "var", "class", "function"]
+                .iter()
+                .any(|keyword| {
+                    before
+                        .trim_end()
+                        .strip_suffix(keyword)
+                        .is_some_and(|before| before.trim_end().ends_with("export"))
+                });
+
+            (before.ends_with("exports.") && after.trim_start().starts_with('='))
+                || declared
+                || after.trim_start().starts_with(": () =>")
+        })
+}
+
+fn validate_bundle(
+    source: &Source,
+    files: &HashMap<String, String>,
+    limits: &Limits,
+) -> Vec<String> {
+    let bundle_path = format!("{}/index.js", source.id());
+
+    let Some(content) = files.get(&bundle_path) else {
+        return vec![format!("the bundle {} does not exist", bundle_path)];
+    };
+
+    let Some(bytes) = decode(content) else {
+        return vec![format!("the bundle {} could not be decoded", bundle_path)];
+    };
+
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return vec!["the bundle is empty".to_string()];
+    }
+
+    let mut problems = vec![];
+
+    if bytes.len() > limits.bundle_max_size {
+        problems.push(format!(
+            "the bundle is {} bytes, the limit is {} bytes",
+            bytes.len(),
+            limits.bundle_max_size
+        ));
+    }
+
+    let Ok(bundle) = String::from_utf8(bytes) else {
+        problems.push("the bundle is not valid UTF-8".to_string());
+        return problems;
+    };
+
+    if !exports_source(&bundle, source.id()) {
+        problems.push(format!(
+            "the bundle does not contain an export of the source {}",
+            source.id()
+        ));
+    }
+
+    for pattern in limits.bundle_denylist.iter() {
+        if bundle.contains(pattern.as_str()) {
+            problems.push(format!(
+                "the bundle contains the denied pattern {}",
+                pattern
+            ));
+        }
+    }
+
+    problems
+}
+
+// Validates the fetched files of every updated extension before they are published
+pub fn validate(
+    updated_extensions: &UpdatedExtensions,
+    versioning: &Versioning,
+    registry_policy: &RegistryPolicy,
+    limits: &Limits,
+) -> Result<(), u8> {
+    let mut limits = limits.clone();
+    limits.restrict(registry_policy);
+    let mut failed = vec![];
+
+    println!("Validating the files of the updated extensions:");
+
+    for (id, files) in updated_extensions.iter() {
+        let Some(source) = versioning.source(id) else {
+            continue;
+        };
+
+        let mut problems = validate_bundle(source, files, &limits);
+        problems.extend(validate_static_files(source, files, &limits));
+
+        if problems.is_empty() {
+            println!("{}: Passed", id);
+        } else {
+            println!("{}: Failed", id);
+
+            for problem in problems.iter() {
+                println!("    {}", problem);
+            }
+
+            failed.push(id.as_str());
+        }
+    }
+
+    if !failed.is_empty() {
+        eprintln!(
+            "The following extensions did not pass the validation: {}",
+            failed.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{source, updated_extensions, versioning};
+
+    const LIMITS: Limits = Limits {
+        icon_max_size: 1024,
+        icon_max_dimension: 256,
+        static_max_size: 2048,
+        bundle_max_size: 1024,
+        bundle_denylist: vec![],
+    };
+
+    // The signature and the header chunk of a PNG image, without any image data
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(width.to_be_bytes());
+        png.extend(height.to_be_bytes());
+        png
+    }
+
+    fn static_problems(files: &[(&str, &[u8])]) -> Vec<String> {
+        let versioning = versioning(vec![source("Example", "1.0.0")]);
+        let updated_extensions = updated_extensions(&[("Example", files)]);
+
+        validate_static_files(
+            versioning.source("Example").unwrap(),
+            &updated_extensions[0].1,
+            &LIMITS,
+        )
+    }
+
+    #[test]
+    fn icons_within_the_limits_pass() {
+        assert!(static_problems(&[("static/icon.png", &png(256, 128))]).is_empty());
+    }
+
+    #[test]
+    fn icons_are_checked_against_the_size_and_dimension_limits() {
+        assert_eq!(
+            static_problems(&[("static/icon.png", &png(512, 128))]),
+            vec!["the icon is 512x128 pixels, the limit is 256x256 pixels"]
+        );
+
+        let mut large_icon = png(64, 64);
+        large_icon.resize(1025, 0);
+
+        assert_eq!(
+            static_problems(&[("static/icon.png", &large_icon)]),
+            vec!["the icon is 1025 bytes, the limit is 1024 bytes"]
+        );
+    }
+
+    #[test]
+    fn icons_have_to_exist_and_be_an_image() {
+        assert_eq!(
+            static_problems(&[("static/other.png", &png(64, 64))]),
+            vec!["the icon Example/static/icon.png does not exist"]
+        );
+        assert_eq!(
+            static_problems(&[("static/icon.png", b"not an image")]),
+            vec!["the icon Example/static/icon.png is not a PNG, JPEG or SVG image"]
+        );
+    }
+
+    #[test]
+    fn static_files_are_limited_in_total() {
+        assert_eq!(
+            static_problems(&[
+                ("static/icon.png", &png(64, 64)),
+                ("static/banner.txt", &[b' '; 2048])
+            ]),
+            vec!["the static files are 2072 bytes in total, the limit is 2048 bytes"]
+        );
+    }
+
+    #[test]
+    fn exports_source_matches_the_export_forms_of_the_toolchain() {
+        assert!(exports_source("exports.MangaDex = MangaDex;", "MangaDex"));
+        assert!(exports_source("export { MangaDex };", "MangaDex"));
+        assert!(exports_source(
+            "export {\n  Source as MangaDex\n};",
+            "MangaDex"
+        ));
+        assert!(exports_source("export const MangaDex = {};", "MangaDex"));
+        assert!(exports_source(
+            "__export(src_exports, {\n  MangaDex: () => MangaDex\n});",
+            "MangaDex"
+        ));
+    }
+
+    #[test]
+    fn exports_source_rejects_other_mentions_of_the_id() {
+        assert!(!exports_source("// export MangaDex", "MangaDex"));
+        assert!(!exports_source("exports.MangaDexPlus = 1;", "MangaDex"));
+        assert!(!exports_source("export { MangaDex as Other };", "MangaDex"));
+        assert!(!exports_source(
+            "const MangaDex = 1; export default MangaDex;",
+            "MangaDex"
+        ));
+    }
+}
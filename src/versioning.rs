@@ -1,11 +1,22 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
 
 use base64::prelude::*;
 use chrono::Utc;
-use node_semver::Version;
-use serde::{Deserialize, Serialize};
+use node_semver::{Range, Version};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{channel::Channel, registry_policy::RegistryPolicy};
+
+pub type UpdatedExtensions = Vec<(String, HashMap<String, String>)>;
+
+// The adult sources of a channel are listed in this versioning file instead,
+// when the registry policy keeps them separate
+pub const ADULT_VERSIONING_PATH: &str = "versioning-adult.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Versioning {
     build_time: String,
@@ -14,21 +25,21 @@ pub struct Versioning {
     sources: Vec<Source>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BuiltWith {
     toolchain: String,
     types: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Repository {
     name: String,
     description: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Source {
     id: String,
@@ -37,13 +48,73 @@ pub struct Source {
     version: String,
     icon: String,
     language: Option<String>,
-    content_rating: String,
+    content_rating: ContentRating,
     badges: Vec<Option<Badges>>,
     capabilities: Option<Capabilities>,
     developers: Vec<Option<Developers>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    build: Option<SourceBuild>,
+    // The archived versions of the source in the registry, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    versions: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+// Recorded by the registry for every published source, the versioning
+// files of the extension repositories do not contain this field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceBuild {
+    toolchain: String,
+    types: String,
+    build_time: String,
+    repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+impl SourceBuild {
+    pub fn repository(&self) -> &str {
+        &self.repository
+    }
+
+    pub fn build_time(&self) -> &str {
+        &self.build_time
+    }
+
+    pub fn new(
+        repository_versioning: &Versioning,
+        repository: String,
+        commit: Option<String>,
+    ) -> SourceBuild {
+        SourceBuild {
+            toolchain: repository_versioning.built_with.toolchain.clone(),
+            types: repository_versioning.built_with.types.clone(),
+            build_time: repository_versioning.build_time.clone(),
+            repository,
+            commit,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ContentRating {
+    Everyone,
+    Mature,
+    Adult,
+}
+
+impl ContentRating {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentRating::Everyone => "EVERYONE",
+            ContentRating::Mature => "MATURE",
+            ContentRating::Adult => "ADULT",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Badges {
     label: String,
@@ -51,14 +122,74 @@ pub struct Badges {
     background_color: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(untagged)]
-enum Capabilities {
-    List(Vec<u8>),
-    Primtitive(u8),
+// The capabilities of a source as bitflags, the toolchain writes them either as a list of
+// flags or as a single bitmask. Both are accepted and written as a sorted list of flags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capabilities(u8);
+
+const CAPABILITY_NAMES: [(u8, &str); 7] = [
+    (1 << 0, "Manga chapters"),
+    (1 << 1, "Manga tracking"),
+    (1 << 2, "Discover sections"),
+    (1 << 3, "Collection management"),
+    (1 << 4, "Cloudflare bypass required"),
+    (1 << 5, "Settings UI"),
+    (1 << 6, "Manga search"),
+];
+
+impl Capabilities {
+    fn flags(&self) -> impl Iterator<Item = (u8, &'static str)> + '_ {
+        CAPABILITY_NAMES
+            .into_iter()
+            .filter(|(flag, _)| self.0 & flag != 0)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.flags().map(|(_, name)| name).collect()
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl<'de> Deserialize<'de> for Capabilities {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Capabilities, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawCapabilities {
+            List(Vec<u64>),
+            Bitmask(u64),
+        }
+
+        let bits = match RawCapabilities::deserialize(deserializer)? {
+            RawCapabilities::List(flags) => flags.into_iter().fold(0, |bits, flag| bits | flag),
+            RawCapabilities::Bitmask(bits) => bits,
+        };
+
+        let known_bits = CAPABILITY_NAMES
+            .iter()
+            .fold(0, |bits, (flag, _)| bits | u64::from(*flag));
+
+        if bits & !known_bits != 0 {
+            return Err(de::Error::custom(format!(
+                "the capabilities contain the unknown bits {:#x}, the known capabilities are: {}",
+                bits & !known_bits,
+                CAPABILITY_NAMES
+                    .iter()
+                    .map(|(flag, name)| format!("{} ({})", name, flag))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )));
+        }
+
+        Ok(Capabilities(bits as u8))
+    }
+}
+
+impl Serialize for Capabilities {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.flags().map(|(flag, _)| flag))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Developers {
     name: String,
@@ -66,134 +197,1720 @@ pub struct Developers {
     github: Option<String>,
 }
 
-pub fn parse_versioning(response_content: &String) -> Result<Versioning, u8> {
-    match BASE64_STANDARD.decode(response_content.replace("\n", "")) {
-        Ok(bytes) => match serde_json::from_slice(bytes.as_slice()) {
-            Ok(versioning) => {
-                println!("Parsed the requested versioning file");
-                Ok(versioning)
+impl Repository {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl Badges {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn text_color(&self) -> &str {
+        &self.text_color
+    }
+
+    pub fn background_color(&self) -> &str {
+        &self.background_color
+    }
+}
+
+impl Developers {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn website(&self) -> Option<&str> {
+        self.website.as_deref()
+    }
+
+    pub fn github(&self) -> Option<&str> {
+        self.github.as_deref()
+    }
+}
+
+pub const DEFAULT_BADGE_TEXT_COLOR: &str = "#ffffff";
+pub const DEFAULT_BADGE_BACKGROUND_COLOR: &str = "#666666";
+
+// The named colors of CSS, sorted so they can be searched
+const NAMED_COLORS: [&str; 148] = [
+    "aliceblue",
+    "antiquewhite",
+    "aqua",
+    "aquamarine",
+    "azure",
+    "beige",
+    "bisque",
+    "black",
+    "blanchedalmond",
+    "blue",
+    "blueviolet",
+    "brown",
+    "burlywood",
+    "cadetblue",
+    "chartreuse",
+    "chocolate",
+    "coral",
+    "cornflowerblue",
+    "cornsilk",
+    "crimson",
+    "cyan",
+    "darkblue",
+    "darkcyan",
+    "darkgoldenrod",
+    "darkgray",
+    "darkgreen",
+    "darkgrey",
+    "darkkhaki",
+    "darkmagenta",
+    "darkolivegreen",
+    "darkorange",
+    "darkorchid",
+    "darkred",
+    "darksalmon",
+    "darkseagreen",
+    "darkslateblue",
+    "darkslategray",
+    "darkslategrey",
+    "darkturquoise",
+    "darkviolet",
+    "deeppink",
+    "deepskyblue",
+    "dimgray",
+    "dimgrey",
+    "dodgerblue",
+    "firebrick",
+    "floralwhite",
+    "forestgreen",
+    "fuchsia",
+    "gainsboro",
+    "ghostwhite",
+    "gold",
+    "goldenrod",
+    "gray",
+    "green",
+    "greenyellow",
+    "grey",
+    "honeydew",
+    "hotpink",
+    "indianred",
+    "indigo",
+    "ivory",
+    "khaki",
+    "lavender",
+    "lavenderblush",
+    "lawngreen",
+    "lemonchiffon",
+    "lightblue",
+    "lightcoral",
+    "lightcyan",
+    "lightgoldenrodyellow",
+    "lightgray",
+    "lightgreen",
+    "lightgrey",
+    "lightpink",
+    "lightsalmon",
+    "lightseagreen",
+    "lightskyblue",
+    "lightslategray",
+    "lightslategrey",
+    "lightsteelblue",
+    "lightyellow",
+    "lime",
+    "limegreen",
+    "linen",
+    "magenta",
+    "maroon",
+    "mediumaquamarine",
+    "mediumblue",
+    "mediumorchid",
+    "mediumpurple",
+    "mediumseagreen",
+    "mediumslateblue",
+    "mediumspringgreen",
+    "mediumturquoise",
+    "mediumvioletred",
+    "midnightblue",
+    "mintcream",
+    "mistyrose",
+    "moccasin",
+    "navajowhite",
+    "navy",
+    "oldlace",
+    "olive",
+    "olivedrab",
+    "orange",
+    "orangered",
+    "orchid",
+    "palegoldenrod",
+    "palegreen",
+    "paleturquoise",
+    "palevioletred",
+    "papayawhip",
+    "peru",
+    "pink",
+    "plum",
+    "powderblue",
+    "purple",
+    "rebeccapurple",
+    "red",
+    "rosybrown",
+    "royalblue",
+    "saddlebrown",
+    "salmon",
+    "sandybrown",
+    "seagreen",
+    "seashell",
+    "sienna",
+    "silver",
+    "skyblue",
+    "slateblue",
+    "slategray",
+    "slategrey",
+    "snow",
+    "springgreen",
+    "steelblue",
+    "tan",
+    "teal",
+    "thistle",
+    "tomato",
+    "transparent",
+    "turquoise",
+    "violet",
+    "wheat",
+    "white",
+    "whitesmoke",
+    "yellow",
+    "yellowgreen",
+];
+
+// Badge colors end up in a style attribute, so only hex colors
+// and the named colors of CSS are accepted
+pub fn is_valid_color(value: &str) -> bool {
+    let is_hex = value.starts_with('#')
+        && matches!(value.len() - 1, 3 | 4 | 6 | 8)
+        && value[1..].chars().all(|c| c.is_ascii_hexdigit());
+    let is_named = NAMED_COLORS
+        .binary_search(&value.to_ascii_lowercase().as_str())
+        .is_ok();
+
+    is_hex || is_named
+}
+
+// GitHub handles consist of up to 39 alphanumeric characters or single hyphens,
+// which can not start or end the handle
+fn is_valid_github_handle(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 39
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !value.starts_with('-')
+        && !value.ends_with('-')
+        && !value.contains("--")
+}
+
+// The toolchain accepts a handle, a mention or a profile link
+fn github_handle(value: &str) -> &str {
+    let value = value.trim();
+    let value = ["https://", "http://"]
+        .iter()
+        .find_map(|scheme| value.strip_prefix(scheme))
+        .unwrap_or(value);
+    let value = value.strip_prefix("www.").unwrap_or(value);
+    let value = value.strip_prefix("github.com/").unwrap_or(value);
+    let value = value.strip_prefix('@').unwrap_or(value);
+
+    value.strip_suffix('/').unwrap_or(value)
+}
+
+fn is_valid_website(value: &str) -> bool {
+    ["https://", "http://"]
+        .iter()
+        .find_map(|scheme| value.strip_prefix(scheme))
+        .is_some_and(|rest| {
+            !rest.is_empty() && !rest.starts_with('/') && !rest.contains(char::is_whitespace)
+        })
+}
+
+impl Source {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn icon(&self) -> &str {
+        &self.icon
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn content_rating(&self) -> ContentRating {
+        self.content_rating
+    }
+
+    pub fn badges(&self) -> impl Iterator<Item = &Badges> {
+        self.badges.iter().flatten()
+    }
+
+    pub fn developers(&self) -> impl Iterator<Item = &Developers> {
+        self.developers.iter().flatten()
+    }
+
+    pub fn build(&self) -> Option<&SourceBuild> {
+        self.build.as_ref()
+    }
+
+    pub fn capabilities(&self) -> Vec<&'static str> {
+        self.capabilities
+            .map(|capabilities| capabilities.names())
+            .unwrap_or_default()
+    }
+
+    // Drops the null and duplicate badges and developers and clears the values which can
+    // not be displayed, returns a description of every change which was made
+    fn normalize(&mut self) -> Vec<String> {
+        let mut changes = vec![];
+
+        let mut badges: Vec<Badges> = vec![];
+
+        for badge in std::mem::take(&mut self.badges) {
+            let Some(mut badge) = badge else {
+                changes.push("Dropped a null badge".to_string());
+                continue;
+            };
+
+            if badge.label.trim().is_empty() {
+                changes.push("Dropped a badge without a label".to_string());
+                continue;
             }
-            Err(err) => {
-                eprintln!(
-                    "An error occurred while deserializing the response content to JSON: {}",
-                    &err
-                );
-                Err(0x1)
+
+            if badges.iter().any(|existing| existing.label == badge.label) {
+                changes.push(format!("Dropped the duplicate badge {}", badge.label));
+                continue;
+            }
+
+            if !is_valid_color(&badge.text_color) {
+                changes.push(format!(
+                    "Replaced the invalid text color \"{}\" of the badge {} by {}",
+                    badge.text_color, badge.label, DEFAULT_BADGE_TEXT_COLOR
+                ));
+                badge.text_color = DEFAULT_BADGE_TEXT_COLOR.to_string();
+            }
+
+            if !is_valid_color(&badge.background_color) {
+                changes.push(format!(
+                    "Replaced the invalid background color \"{}\" of the badge {} by {}",
+                    badge.background_color, badge.label, DEFAULT_BADGE_BACKGROUND_COLOR
+                ));
+                badge.background_color = DEFAULT_BADGE_BACKGROUND_COLOR.to_string();
+            }
+
+            badges.push(badge);
+        }
+
+        self.badges = badges.into_iter().map(Some).collect();
+
+        let mut developers: Vec<Developers> = vec![];
+
+        for developer in std::mem::take(&mut self.developers) {
+            let Some(mut developer) = developer else {
+                changes.push("Dropped a null developer".to_string());
+                continue;
+            };
+
+            if developer.name.trim().is_empty() {
+                changes.push("Dropped a developer without a name".to_string());
+                continue;
+            }
+
+            if let Some(github) = developer.github.take() {
+                let handle = github_handle(&github);
+
+                if !is_valid_github_handle(handle) {
+                    changes.push(format!(
+                        "Removed the invalid GitHub handle \"{}\" of the developer {}",
+                        github, developer.name
+                    ));
+                } else {
+                    if handle != github {
+                        changes.push(format!(
+                            "Replaced the GitHub handle \"{}\" of the developer {} by {}",
+                            github, developer.name, handle
+                        ));
+                    }
+
+                    developer.github = Some(handle.to_string());
+                }
+            }
+
+            if let Some(website) = developer.website.take() {
+                if is_valid_website(&website) {
+                    developer.website = Some(website);
+                } else {
+                    changes.push(format!(
+                        "Removed the invalid website \"{}\" of the developer {}",
+                        website, developer.name
+                    ));
+                }
+            }
+
+            if developers.contains(&developer) {
+                changes.push(format!(
+                    "Dropped the duplicate developer {}",
+                    developer.name
+                ));
+                continue;
+            }
+
+            developers.push(developer);
+        }
+
+        self.developers = developers.into_iter().map(Some).collect();
+
+        changes
+    }
+
+    // Returns the names of the fields, other than the id and version,
+    // which differ between both sources.
+    fn changed_metadata(&self, other: &Source) -> Vec<&'static str> {
+        let mut fields = vec![];
+
+        if self.name != other.name {
+            fields.push("name");
+        }
+        if self.description != other.description {
+            fields.push("description");
+        }
+        if self.icon != other.icon {
+            fields.push("icon");
+        }
+        if self.language != other.language {
+            fields.push("language");
+        }
+        if self.content_rating != other.content_rating {
+            fields.push("contentRating");
+        }
+        if self.badges != other.badges {
+            fields.push("badges");
+        }
+        if self.capabilities != other.capabilities {
+            fields.push("capabilities");
+        }
+        if self.developers != other.developers {
+            fields.push("developers");
+        }
+
+        fields
+    }
+}
+
+// The layout used when writing the versioning file back to the registry,
+// a stable layout keeps the registry commit diffs readable per source.
+#[derive(Debug)]
+pub struct OutputFormat {
+    pub indent: usize,
+    pub order: SourceOrder,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceOrder {
+    Id,
+    Preserve,
+}
+
+// The rules which are applied while merging the repository versioning
+// file into the registry versioning file.
+#[derive(Debug)]
+pub struct Policy {
+    pub allow_downgrade: Vec<String>,
+    pub metadata: MetadataPolicy,
+    pub content: ContentPolicy,
+    pub types: TypesPolicy,
+    pub channel: Channel,
+}
+
+// How metadata changes of a source without a version bump are handled
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataPolicy {
+    Update,
+    Warn,
+    RequireBump,
+}
+
+// How changed files of a source without a version bump are handled
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentPolicy {
+    Fail,
+    Warn,
+}
+
+// Whether @paperback/types prereleases are accepted
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypesPrerelease {
+    Allow,
+    Reject,
+}
+
+// Which @paperback/types versions the registry accepts, without a configured
+// range every version equal to or higher than the one of the registry is accepted.
+#[derive(Debug)]
+pub struct TypesPolicy {
+    pub registry_branch: String,
+    pub range: Option<Range>,
+    pub allow_prerelease: bool,
+}
+
+impl TypesPolicy {
+    // The range of the registry branch is used, otherwise the range without a branch
+    pub fn new(
+        ranges: &[(Option<String>, Range)],
+        prerelease: TypesPrerelease,
+        registry_branch: &str,
+    ) -> TypesPolicy {
+        let mut range = None;
+
+        for (branch, branch_range) in ranges.iter() {
+            match branch {
+                Some(branch) if branch == registry_branch => {
+                    range = Some(branch_range.clone());
+                    break;
+                }
+                Some(_) => (),
+                None => range = Some(branch_range.clone()),
             }
-        },
+        }
+
+        TypesPolicy {
+            registry_branch: registry_branch.to_string(),
+            range,
+            allow_prerelease: matches!(prerelease, TypesPrerelease::Allow),
+        }
+    }
+}
+
+// Parses a list of ranges separated by a semicolon, every range may be
+// prefixed by the registry branch it applies to, e.g. "master=^0.9.0-alpha".
+pub fn parse_types_ranges(value: &str) -> Result<Vec<(Option<String>, Range)>, String> {
+    let mut ranges = vec![];
+
+    for entry in value.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (branch, raw_range) = match entry.split_once('=') {
+            // Comparators such as ">=0.9.0" also contain an equal sign
+            Some((branch, raw_range))
+                if !branch.trim().is_empty()
+                    && !branch.trim().ends_with(['<', '>', '='])
+                    && !branch.trim().contains(' ') =>
+            {
+                (Some(branch.trim().to_string()), raw_range.trim())
+            }
+            _ => (None, entry),
+        };
+
+        match Range::parse(raw_range) {
+            Ok(range) => ranges.push((branch, range)),
+            Err(err) => return Err(format!("{}: {}", raw_range, err)),
+        }
+    }
+
+    Ok(ranges)
+}
+
+fn check_types_compatibility(
+    registry_built_with: &BuiltWith,
+    repository_built_with: &BuiltWith,
+    policy: &TypesPolicy,
+) -> Result<(), u8> {
+    let repository_types = match repository_built_with.types.parse::<Version>() {
+        Ok(version) => version,
         Err(err) => {
             eprintln!(
-                "An error occurred while base64 decoding the response content: {}",
-                &err
+                "The repository was build with an unparsable @paperback/types version {}: {}",
+                repository_built_with.types, &err
             );
-            Err(0x1)
+            return Err(0x1);
         }
+    };
+
+    let range = match &policy.range {
+        Some(range) => range.clone(),
+        None => {
+            let registry_types = registry_built_with
+                .types
+                .parse::<Version>()
+                .unwrap_or(Version::from((0, 9, 0)));
+
+            match Range::parse(format!(">={}", registry_types)) {
+                Ok(range) => range,
+                Err(err) => {
+                    eprintln!(
+                        "The @paperback/types range of the registry version {} could not be parsed: {}",
+                        registry_types, &err
+                    );
+                    return Err(0x1);
+                }
+            }
+        }
+    };
+
+    println!(
+        "Accepted @paperback/types range for the registry branch {}: {}",
+        policy.registry_branch, range
+    );
+
+    if repository_types.is_prerelease() && !policy.allow_prerelease {
+        eprintln!(
+            "The repository was build with the @paperback/types prerelease {}, prereleases are not accepted by the registry branch {}",
+            repository_types, policy.registry_branch
+        );
+        return Err(0x1);
+    }
+
+    if !range.satisfies(&repository_types) {
+        eprintln!(
+            "The repository was build with the @paperback/types version {}, which is not accepted by the range {} of the registry branch {}",
+            repository_types, range, policy.registry_branch
+        );
+        return Err(0x1);
+    }
+
+    println!(
+        "The repository was build with the accepted @paperback/types version {}",
+        repository_types
+    );
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Added,
+    Updated,
+    Unchanged,
+    Downgraded { allowed: bool },
+    MetadataUpdated,
+    MetadataDrift { blocked: bool },
+    ContentDrift { blocked: bool },
+}
+
+#[derive(Debug)]
+pub struct SourceChange {
+    pub id: String,
+    pub name: String,
+    pub old_version: Option<String>,
+    pub new_version: String,
+    pub outcome: Outcome,
+    pub changed_fields: Vec<&'static str>,
+    pub capabilities: Vec<&'static str>,
+}
+
+impl SourceChange {
+    fn new(
+        source: &Source,
+        old_version: Option<String>,
+        outcome: Outcome,
+        changed_fields: Vec<&'static str>,
+    ) -> SourceChange {
+        SourceChange {
+            id: source.id.clone(),
+            name: source.name.clone(),
+            old_version,
+            new_version: source.version.clone(),
+            outcome,
+            changed_fields,
+            capabilities: source.capabilities(),
+        }
+    }
+
+    // A registry entry which is restored from an earlier registry version or copied from
+    // another channel, a lower version than the one it replaces is an allowed downgrade
+    pub fn restored(source: &Source, old_version: Option<&str>) -> SourceChange {
+        let outcome = match old_version {
+            None => Outcome::Added,
+            Some(old_version) => {
+                match (
+                    old_version.parse::<Version>(),
+                    source.version.parse::<Version>(),
+                ) {
+                    (Ok(old_version), Ok(new_version)) if new_version < old_version => {
+                        Outcome::Downgraded { allowed: true }
+                    }
+                    _ => Outcome::Updated,
+                }
+            }
+        };
+
+        SourceChange::new(source, old_version.map(str::to_string), outcome, vec![])
+    }
+
+    // Whether the change ends up in the registry
+    pub fn is_published(&self) -> bool {
+        matches!(
+            self.outcome,
+            Outcome::Added
+                | Outcome::Updated
+                | Outcome::Downgraded { allowed: true }
+                | Outcome::MetadataUpdated
+        )
     }
 }
 
 pub fn update_registry_versioning(
     registry_versioning: &mut Versioning,
     repository_versioning: &Versioning,
-) -> Result<Vec<(String, HashMap<String, String>)>, u8> {
+    source_build: &SourceBuild,
+    changed_content: &HashSet<String>,
+    policy: &Policy,
+    registry_policy: &RegistryPolicy,
+) -> Result<(UpdatedExtensions, Vec<SourceChange>), u8> {
     let mut updated_extensions = vec![];
+    let mut changes = vec![];
+    let mut invalid = false;
+    let mut rejected = vec![];
+    let mut metadata_updated = false;
 
-    if registry_versioning
-        .built_with
-        .types
-        .parse::<Version>()
-        .unwrap_or_else(|_| Version::parse("0.9.0").unwrap())
-        > repository_versioning
-            .built_with
-            .types
-            .parse::<Version>()
-            .unwrap_or_else(|_| Version::parse("0.0.0").unwrap())
-    {
+    if !registry_policy.allows_repository(source_build.repository()) {
         eprintln!(
-            "The repository was build with an invalid @paperback/types version {}, expected version {} or higher",
-            repository_versioning.built_with.types,
-            registry_versioning.built_with.types
+            "The repository {} is not allowed to publish to the registry by its policy",
+            source_build.repository()
         );
         return Err(0x1);
     }
 
+    check_types_compatibility(
+        &registry_versioning.built_with,
+        &repository_versioning.built_with,
+        &policy.types,
+    )?;
+
     println!("Comparing the extensions of both versioning files:");
 
     for repository_extension in repository_versioning.sources.iter() {
-        let mut new = true;
-
         print!("{}: ", repository_extension.name);
 
-        for (index, registry_extension) in registry_versioning.sources.iter().enumerate() {
-            if repository_extension.id != registry_extension.id {
+        let repository_version = match repository_extension.version.parse::<Version>() {
+            Ok(version) => version,
+            Err(err) => {
+                println!("The repository version is invalid -> Skipping");
+                eprintln!(
+                    "The repository version {} of {} could not be parsed: {}",
+                    repository_extension.version, repository_extension.id, &err
+                );
+                invalid = true;
                 continue;
             }
+        };
 
-            println!("Already exists in the registry");
+        if repository_version.is_prerelease() && !policy.channel.allows_prerelease() {
+            println!("The repository version is a prerelease -> Skipping");
+            eprintln!(
+                "The repository version {} of {} is a prerelease, which is only allowed on the beta and nightly channels",
+                repository_extension.version, repository_extension.id
+            );
+            invalid = true;
+            continue;
+        }
 
-            new = false;
+        // The registry policy only applies to the sources which are published
+        let violations =
+            registry_policy.violations(source_build.repository(), repository_extension);
 
-            if repository_extension
-                .version
-                .parse::<Version>()
-                .unwrap_or_else(|_| Version::parse("0.0.0").unwrap())
-                > registry_extension.version.parse::<Version>().unwrap()
-            {
-                registry_versioning
-                    .sources
-                    .insert(index, repository_extension.clone());
+        let Some(index) = registry_versioning
+            .sources
+            .iter()
+            .position(|registry_extension| registry_extension.id == repository_extension.id)
+        else {
+            if !violations.is_empty() {
+                println!(
+                    "Does not exist in the registry but violates the registry policy -> Skipping"
+                );
+                print_violations(&violations);
+                rejected.push(repository_extension.id.as_str());
+                continue;
+            }
 
-                updated_extensions.push((repository_extension.id.clone(), HashMap::new()));
+            registry_versioning.sources.push(Source {
+                build: Some(source_build.clone()),
+                ..repository_extension.clone()
+            });
+
+            updated_extensions.push((repository_extension.id.clone(), HashMap::new()));
+            changes.push(SourceChange::new(
+                repository_extension,
+                None,
+                Outcome::Added,
+                vec![],
+            ));
+
+            println!("Does not exist in the registry -> Adding");
+            continue;
+        };
+
+        println!("Already exists in the registry");
+
+        let registry_extension = &registry_versioning.sources[index];
+
+        let registry_version = match registry_extension.version.parse::<Version>() {
+            Ok(version) => version,
+            Err(err) => {
+                println!("The registry version is invalid -> Skipping");
+                eprintln!(
+                    "The registry version {} of {} could not be parsed: {}",
+                    registry_extension.version, registry_extension.id, &err
+                );
+                invalid = true;
+                continue;
+            }
+        };
+
+        let changed_fields = repository_extension.changed_metadata(registry_extension);
 
+        let outcome = match repository_version.cmp(&registry_version) {
+            Ordering::Greater => {
                 println!("A newer version was found -> Updating");
-            } else {
+                Outcome::Updated
+            }
+            Ordering::Equal if changed_content.contains(&repository_extension.id) => {
+                match policy.content {
+                    ContentPolicy::Fail => {
+                        println!("The version was unchanged but the files changed -> Blocking, bump the version to publish these changes");
+                        Outcome::ContentDrift { blocked: true }
+                    }
+                    ContentPolicy::Warn => {
+                        println!("The version was unchanged but the files changed -> Leaving untouched, bump the version to publish these changes");
+                        Outcome::ContentDrift { blocked: false }
+                    }
+                }
+            }
+            Ordering::Equal if changed_fields.is_empty() => {
                 println!("The version was unchanged -> Leaving untouched");
+                Outcome::Unchanged
+            }
+            Ordering::Equal => match policy.metadata {
+                MetadataPolicy::Update => {
+                    println!(
+                        "The version was unchanged but the metadata changed ({}) -> Updating the metadata",
+                        changed_fields.join(", ")
+                    );
+                    Outcome::MetadataUpdated
+                }
+                MetadataPolicy::Warn => {
+                    println!(
+                        "The version was unchanged but the metadata changed ({}) -> Leaving untouched, bump the version to publish these changes",
+                        changed_fields.join(", ")
+                    );
+                    Outcome::MetadataDrift { blocked: false }
+                }
+                MetadataPolicy::RequireBump => {
+                    println!(
+                        "The version was unchanged but the metadata changed ({}) -> Blocking, bump the version to publish these changes",
+                        changed_fields.join(", ")
+                    );
+                    Outcome::MetadataDrift { blocked: true }
+                }
+            },
+            Ordering::Less if policy.allow_downgrade.contains(&repository_extension.id) => {
+                println!(
+                    "An older version was found ({} -> {}), the downgrade was explicitly allowed -> Downgrading",
+                    registry_extension.version, repository_extension.version
+                );
+                Outcome::Downgraded { allowed: true }
             }
+            Ordering::Less => {
+                println!(
+                    "An older version was found ({} -> {}) -> Blocking, use --allow-downgrade {} to allow this",
+                    registry_extension.version, repository_extension.version, repository_extension.id
+                );
+                Outcome::Downgraded { allowed: false }
+            }
+        };
 
-            break;
+        if !violations.is_empty()
+            && matches!(
+                outcome,
+                Outcome::Updated | Outcome::Downgraded { allowed: true } | Outcome::MetadataUpdated
+            )
+        {
+            println!("The source violates the registry policy -> Skipping instead");
+            print_violations(&violations);
+            rejected.push(repository_extension.id.as_str());
+            continue;
         }
 
-        if new {
-            registry_versioning
-                .sources
-                .push(repository_extension.clone());
+        changes.push(SourceChange::new(
+            repository_extension,
+            Some(registry_extension.version.clone()),
+            outcome,
+            changed_fields,
+        ));
 
-            updated_extensions.push((repository_extension.id.clone(), HashMap::new()));
+        match outcome {
+            Outcome::Updated | Outcome::Downgraded { allowed: true } => {
+                registry_versioning.sources[index] = Source {
+                    build: Some(source_build.clone()),
+                    versions: std::mem::take(&mut registry_versioning.sources[index].versions),
+                    ..repository_extension.clone()
+                };
 
-            println!("Does not exist in the registry -> Adding");
+                updated_extensions.push((repository_extension.id.clone(), HashMap::new()));
+            }
+            // Only the versioning file changes, the files of the extension are left untouched
+            Outcome::MetadataUpdated => {
+                registry_versioning.sources[index] = Source {
+                    build: registry_versioning.sources[index].build.take(),
+                    versions: std::mem::take(&mut registry_versioning.sources[index].versions),
+                    ..repository_extension.clone()
+                };
+
+                metadata_updated = true;
+            }
+            _ => (),
         }
     }
 
-    if updated_extensions.len() == 0 {
+    if invalid {
+        eprintln!("One or more extensions have an invalid version");
+        return Err(0x1);
+    }
+
+    if !rejected.is_empty() {
+        eprintln!(
+            "The following extensions violate the policy of the registry: {}",
+            rejected.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    let blocked_downgrades: Vec<&str> = changes
+        .iter()
+        .filter(|change| matches!(change.outcome, Outcome::Downgraded { allowed: false }))
+        .map(|change| change.id.as_str())
+        .collect();
+
+    if !blocked_downgrades.is_empty() {
+        eprintln!(
+            "The following extensions would be downgraded without being explicitly allowed: {}",
+            blocked_downgrades.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    let blocked_metadata: Vec<&str> = changes
+        .iter()
+        .filter(|change| matches!(change.outcome, Outcome::MetadataDrift { blocked: true }))
+        .map(|change| change.id.as_str())
+        .collect();
+
+    if !blocked_metadata.is_empty() {
+        eprintln!(
+            "The following extensions changed their metadata without a version bump: {}",
+            blocked_metadata.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    let blocked_content: Vec<&str> = changes
+        .iter()
+        .filter(|change| matches!(change.outcome, Outcome::ContentDrift { blocked: true }))
+        .map(|change| change.id.as_str())
+        .collect();
+
+    if !blocked_content.is_empty() {
+        eprintln!(
+            "The following extensions changed their files without a version bump: {}",
+            blocked_content.join(", ")
+        );
+        return Err(0x1);
+    }
+
+    if updated_extensions.is_empty() && !metadata_updated {
         eprintln!("There are no extensions to update");
         return Err(0x1);
     }
 
     registry_versioning.build_time = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-    registry_versioning.built_with.toolchain = repository_versioning.built_with.toolchain.clone();
-    registry_versioning.built_with.types = repository_versioning.built_with.types.clone();
+    registry_versioning.aggregate_built_with();
 
     println!("Updated the local copy of the registry versioning file");
 
-    Ok(updated_extensions)
+    Ok((updated_extensions, changes))
+}
+
+fn print_violations(violations: &[String]) {
+    for violation in violations.iter() {
+        println!("    {}", violation);
+    }
+}
+
+pub fn print_summary(changes: &[SourceChange]) {
+    println!("Summary of the compared extensions:");
+
+    for change in changes.iter() {
+        let outcome = match change.outcome {
+            Outcome::Added => "added",
+            Outcome::Updated => "updated",
+            Outcome::Unchanged => "unchanged",
+            Outcome::Downgraded { allowed: true } => "downgraded",
+            Outcome::Downgraded { allowed: false } => "downgrade blocked",
+            Outcome::MetadataUpdated => "metadata updated",
+            Outcome::MetadataDrift { blocked: false } => "metadata changed",
+            Outcome::MetadataDrift { blocked: true } => "metadata change blocked",
+            Outcome::ContentDrift { blocked: false } => "files changed",
+            Outcome::ContentDrift { blocked: true } => "file change blocked",
+        };
+
+        println!(
+            "{} ({}): {} -> {} [{}]",
+            change.name,
+            change.id,
+            change.old_version.as_deref().unwrap_or("none"),
+            change.new_version,
+            outcome
+        );
+
+        if !change.changed_fields.is_empty() {
+            println!("    changed fields: {}", change.changed_fields.join(", "));
+        }
+
+        if change.is_published() && !change.capabilities.is_empty() {
+            println!("    capabilities: {}", change.capabilities.join(", "));
+        }
+    }
+}
+
+fn minimum_version<'a>(versions: impl Iterator<Item = &'a String>) -> Option<String> {
+    versions
+        .filter_map(|raw_version| {
+            raw_version
+                .parse::<Version>()
+                .ok()
+                .map(|version| (version, raw_version))
+        })
+        .min_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, raw_version)| raw_version.clone())
 }
 
 impl Versioning {
-    pub fn to_base64(&self) -> Result<String, u8> {
-        let p_versioning_string = serde_json::to_string(&self);
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    pub fn source(&self, id: &str) -> Option<&Source> {
+        self.sources.iter().find(|source| source.id == id)
+    }
+
+    // Replaces the source with the same id by a source of a previous registry commit,
+    // its archived versions are kept as the archive is not part of the restored files
+    pub fn restore_source(&mut self, source: Source) {
+        match self
+            .sources
+            .iter()
+            .position(|current| current.id == source.id)
+        {
+            Some(index) => {
+                self.sources[index] = Source {
+                    versions: std::mem::take(&mut self.sources[index].versions),
+                    ..source
+                }
+            }
+            None => self.sources.push(Source {
+                versions: vec![],
+                ..source
+            }),
+        }
+
+        self.build_time = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        self.aggregate_built_with();
+    }
+
+    // An empty versioning file with the header of the given one, used when a channel is
+    // published to for the first time
+    pub fn without_sources(&self) -> Versioning {
+        Versioning {
+            sources: vec![],
+            ..self.clone()
+        }
+    }
+
+    // Adds the sources of the separate versioning file of the same channel
+    pub fn merge(&mut self, other: Versioning) {
+        self.sources.extend(other.sources);
+        self.aggregate_built_with();
+    }
+
+    // Splits the adult sources off into a separate versioning file
+    pub fn split_adult(&self) -> (Versioning, Versioning) {
+        let (adult_sources, sources): (Vec<Source>, Vec<Source>) = self
+            .sources
+            .iter()
+            .cloned()
+            .partition(|source| source.content_rating == ContentRating::Adult);
+
+        let mut versioning = Versioning {
+            sources,
+            ..self.clone()
+        };
+        versioning.aggregate_built_with();
+
+        let mut adult_versioning = Versioning {
+            sources: adult_sources,
+            ..self.clone()
+        };
+        adult_versioning.aggregate_built_with();
+
+        (versioning, adult_versioning)
+    }
+
+    // Normalizes the badges and developers of every source, the toolchain writes nulls and
+    // duplicates into these lists which would otherwise be carried into the registry
+    pub fn normalize(&mut self) {
+        for source in self.sources.iter_mut() {
+            let changes = source.normalize();
+
+            if !changes.is_empty() {
+                println!(
+                    "Normalized the badges and developers of {} ({}):",
+                    source.name, source.id
+                );
+
+                for change in changes.iter() {
+                    println!("    {}", change);
+                }
+            }
+        }
+    }
+
+    pub fn set_versions(&mut self, id: &str, versions: Vec<String>) {
+        if let Some(source) = self.sources.iter_mut().find(|source| source.id == id) {
+            source.versions = versions;
+        }
+    }
+
+    // The registry contains sources of many repositories, instead of the values of
+    // the last publishing repository the header lists the lowest toolchain and
+    // types versions any source with recorded build metadata was built with.
+    fn aggregate_built_with(&mut self) {
+        let builds: Vec<&SourceBuild> = self
+            .sources
+            .iter()
+            .filter_map(|source| source.build.as_ref())
+            .collect();
+
+        if let Some(toolchain) = minimum_version(builds.iter().map(|build| &build.toolchain)) {
+            self.built_with.toolchain = toolchain;
+        }
+
+        if let Some(types) = minimum_version(builds.iter().map(|build| &build.types)) {
+            self.built_with.types = types;
+        }
+    }
+
+    pub fn to_base64(&self, format: &OutputFormat) -> Result<String, u8> {
+        let mut versioning = self.clone();
+
+        if let SourceOrder::Id = format.order {
+            versioning.sources.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+
+        let mut buffer = vec![];
+
+        let p_serialized = if format.indent == 0 {
+            versioning.serialize(&mut serde_json::Serializer::new(&mut buffer))
+        } else {
+            let indent = " ".repeat(format.indent);
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+
+            versioning.serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buffer,
+                formatter,
+            ))
+        };
+
+        match p_serialized {
+            Ok(_) => {
+                buffer.push(b'\n');
 
-        match p_versioning_string {
-            Ok(versioning_string) => {
                 println!("Encoded the versioning file to base64");
 
-                Ok(BASE64_STANDARD.encode(&versioning_string))
+                Ok(BASE64_STANDARD.encode(&buffer))
             }
             Err(err) => {
                 eprintln!(
                     "An error occurred while serializing the versioning struct: {}",
                     &err
                 );
-                return Err(0x1);
+                Err(0x1)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{source, versioning};
+
+    const REPOSITORY: &str = "paperback-community/extensions";
+
+    fn policy(metadata: MetadataPolicy, allow_downgrade: &[&str]) -> Policy {
+        Policy {
+            allow_downgrade: allow_downgrade.iter().map(|id| id.to_string()).collect(),
+            metadata,
+            content: ContentPolicy::Fail,
+            types: TypesPolicy::new(&[], TypesPrerelease::Allow, "master"),
+            channel: Channel::Stable,
+        }
+    }
+
+    fn publish(
+        registry_versioning: &mut Versioning,
+        repository_versioning: &Versioning,
+        policy: &Policy,
+    ) -> Result<(UpdatedExtensions, Vec<SourceChange>), u8> {
+        update_registry_versioning(
+            registry_versioning,
+            repository_versioning,
+            &SourceBuild::new(repository_versioning, REPOSITORY.to_string(), None),
+            &HashSet::new(),
+            policy,
+            &RegistryPolicy::default(),
+        )
+    }
+
+    fn updated_ids(updated_extensions: &UpdatedExtensions) -> Vec<&str> {
+        updated_extensions
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect()
+    }
+
+    fn outcomes(changes: &[SourceChange]) -> Vec<Outcome> {
+        changes.iter().map(|change| change.outcome).collect()
+    }
+
+    fn decoded(versioning: &Versioning, indent: usize, order: SourceOrder) -> String {
+        let encoded = versioning
+            .to_base64(&OutputFormat { indent, order })
+            .unwrap();
+
+        String::from_utf8(BASE64_STANDARD.decode(encoded).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn versioning_files_are_sorted_by_id_and_indented() {
+        let sorted = decoded(
+            &versioning(vec![source("B", "1.0.0"), source("A", "1.0.0")]),
+            4,
+            SourceOrder::Id,
+        );
+
+        assert!(sorted.starts_with("{\n    \"buildTime\""));
+        assert!(sorted.ends_with("}\n"));
+        assert!(sorted.find("\"id\": \"A\"").unwrap() < sorted.find("\"id\": \"B\"").unwrap());
+    }
+
+    #[test]
+    fn versioning_files_can_be_compact_in_their_current_order() {
+        let compact = decoded(
+            &versioning(vec![source("B", "1.0.0"), source("A", "1.0.0")]),
+            0,
+            SourceOrder::Preserve,
+        );
+
+        assert_eq!(compact.matches('\n').count(), 1);
+        assert!(compact.find("\"id\":\"B\"").unwrap() < compact.find("\"id\":\"A\"").unwrap());
+    }
+
+    #[test]
+    fn new_sources_are_added_and_newer_versions_updated() {
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0")]);
+        let repository_versioning = versioning(vec![source("A", "1.1.0"), source("B", "1.0.0")]);
+
+        let (updated_extensions, changes) = publish(
+            &mut registry_versioning,
+            &repository_versioning,
+            &policy(MetadataPolicy::Warn, &[]),
+        )
+        .unwrap();
+
+        assert_eq!(updated_ids(&updated_extensions), vec!["A", "B"]);
+        assert_eq!(outcomes(&changes), vec![Outcome::Updated, Outcome::Added]);
+        assert_eq!(changes[0].old_version.as_deref(), Some("1.0.0"));
+        assert_eq!(registry_versioning.source("A").unwrap().version(), "1.1.0");
+        assert_eq!(registry_versioning.source("B").unwrap().version(), "1.0.0");
+    }
+
+    #[test]
+    fn unchanged_sources_are_not_published() {
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0")]);
+        let repository_versioning = versioning(vec![source("A", "1.0.0")]);
+
+        assert!(publish(
+            &mut registry_versioning,
+            &repository_versioning,
+            &policy(MetadataPolicy::Warn, &[]),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn downgrades_are_blocked_unless_allowed() {
+        let repository_versioning = versioning(vec![source("A", "1.0.0")]);
+
+        let mut registry_versioning = versioning(vec![source("A", "2.0.0")]);
+        assert!(publish(
+            &mut registry_versioning,
+            &repository_versioning,
+            &policy(MetadataPolicy::Warn, &["B"]),
+        )
+        .is_err());
+        assert_eq!(registry_versioning.source("A").unwrap().version(), "2.0.0");
+
+        let mut registry_versioning = versioning(vec![source("A", "2.0.0")]);
+        let (updated_extensions, changes) = publish(
+            &mut registry_versioning,
+            &repository_versioning,
+            &policy(MetadataPolicy::Warn, &["A"]),
+        )
+        .unwrap();
+
+        assert_eq!(updated_ids(&updated_extensions), vec!["A"]);
+        assert_eq!(
+            outcomes(&changes),
+            vec![Outcome::Downgraded { allowed: true }]
+        );
+        assert_eq!(registry_versioning.source("A").unwrap().version(), "1.0.0");
+    }
+
+    #[test]
+    fn unparsable_versions_are_rejected() {
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0")]);
+        assert!(publish(
+            &mut registry_versioning,
+            &versioning(vec![source("A", "one"), source("B", "1.0.0")]),
+            &policy(MetadataPolicy::Warn, &[]),
+        )
+        .is_err());
+
+        let mut registry_versioning = versioning(vec![source("A", "one")]);
+        assert!(publish(
+            &mut registry_versioning,
+            &versioning(vec![source("A", "1.0.0"), source("B", "1.0.0")]),
+            &policy(MetadataPolicy::Warn, &[]),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn metadata_changes_update_the_entry_and_keep_its_build_and_versions() {
+        let mut registry_source = source("A", "1.0.0");
+        registry_source["build"] = json!({
+            "toolchain": "0.8.0",
+            "types": "0.8.0",
+            "buildTime": "2023-01-01T00:00:00.000Z",
+            "repository": REPOSITORY
+        });
+        registry_source["versions"] = json!(["1.0.0", "0.9.0"]);
+        let mut registry_versioning = versioning(vec![registry_source.clone()]);
+
+        let mut repository_source = source("A", "1.0.0");
+        repository_source["name"] = json!("Renamed");
+
+        let (updated_extensions, changes) = publish(
+            &mut registry_versioning,
+            &versioning(vec![repository_source]),
+            &policy(MetadataPolicy::Update, &[]),
+        )
+        .unwrap();
+
+        assert!(updated_extensions.is_empty());
+        assert_eq!(outcomes(&changes), vec![Outcome::MetadataUpdated]);
+        assert_eq!(changes[0].changed_fields, vec!["name"]);
+
+        let updated_source = serde_json::to_value(registry_versioning.source("A")).unwrap();
+        assert_eq!(updated_source["name"], "Renamed");
+        assert_eq!(updated_source["build"], registry_source["build"]);
+        assert_eq!(updated_source["versions"], registry_source["versions"]);
+    }
+
+    #[test]
+    fn metadata_changes_are_left_untouched_by_the_warn_policy() {
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0"), source("B", "1.0.0")]);
+
+        let mut repository_source = source("A", "1.0.0");
+        repository_source["description"] = json!("Changed");
+
+        let (updated_extensions, changes) = publish(
+            &mut registry_versioning,
+            &versioning(vec![repository_source, source("B", "1.1.0")]),
+            &policy(MetadataPolicy::Warn, &[]),
+        )
+        .unwrap();
+
+        assert_eq!(updated_ids(&updated_extensions), vec!["B"]);
+        assert_eq!(
+            outcomes(&changes),
+            vec![Outcome::MetadataDrift { blocked: false }, Outcome::Updated]
+        );
+        assert_eq!(changes[0].changed_fields, vec!["description"]);
+        assert_eq!(
+            serde_json::to_value(registry_versioning.source("A")).unwrap()["description"],
+            "An example source"
+        );
+    }
+
+    #[test]
+    fn metadata_changes_are_blocked_by_the_require_bump_policy() {
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0"), source("B", "1.0.0")]);
+
+        let mut repository_source = source("A", "1.0.0");
+        repository_source["icon"] = json!("other.png");
+
+        assert!(publish(
+            &mut registry_versioning,
+            &versioning(vec![repository_source, source("B", "1.1.0")]),
+            &policy(MetadataPolicy::RequireBump, &[]),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn changed_metadata_lists_the_differing_fields() {
+        let registry_source: Source = serde_json::from_value(source("A", "1.0.0")).unwrap();
+
+        let mut repository_source = source("A", "2.0.0");
+        repository_source["name"] = json!("Renamed");
+        repository_source["language"] = json!("nl");
+        let repository_source: Source = serde_json::from_value(repository_source).unwrap();
+
+        assert_eq!(
+            repository_source.changed_metadata(&registry_source),
+            vec!["name", "language"]
+        );
+        assert!(registry_source
+            .changed_metadata(&registry_source)
+            .is_empty());
+    }
+
+    #[test]
+    fn changed_files_without_a_version_bump_follow_the_content_policy() {
+        let repository_versioning = versioning(vec![source("A", "1.0.0"), source("B", "1.1.0")]);
+        let changed_content = HashSet::from(["A".to_string()]);
+        let source_build = SourceBuild::new(&repository_versioning, REPOSITORY.to_string(), None);
+
+        let mut content_policy = policy(MetadataPolicy::Warn, &[]);
+
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0"), source("B", "1.0.0")]);
+        assert!(update_registry_versioning(
+            &mut registry_versioning,
+            &repository_versioning,
+            &source_build,
+            &changed_content,
+            &content_policy,
+            &RegistryPolicy::default(),
+        )
+        .is_err());
+
+        content_policy.content = ContentPolicy::Warn;
+
+        let mut registry_versioning = versioning(vec![source("A", "1.0.0"), source("B", "1.0.0")]);
+        let (updated_extensions, changes) = update_registry_versioning(
+            &mut registry_versioning,
+            &repository_versioning,
+            &source_build,
+            &changed_content,
+            &content_policy,
+            &RegistryPolicy::default(),
+        )
+        .unwrap();
+
+        assert_eq!(updated_ids(&updated_extensions), vec!["B"]);
+        assert_eq!(
+            outcomes(&changes),
+            vec![Outcome::ContentDrift { blocked: false }, Outcome::Updated]
+        );
+    }
+
+    fn built_with(types: &str) -> BuiltWith {
+        BuiltWith {
+            toolchain: "0.9.0".to_string(),
+            types: types.to_string(),
+        }
+    }
+
+    #[test]
+    fn types_policies_prefer_the_range_of_the_registry_branch() {
+        let ranges = parse_types_ranges("master=^0.9.0-alpha; >=1.0.0").unwrap();
+
+        let master = TypesPolicy::new(&ranges, TypesPrerelease::Allow, "master");
+        assert!(master
+            .range
+            .unwrap()
+            .satisfies(&"0.9.0-alpha.2".parse().unwrap()));
+
+        let develop = TypesPolicy::new(&ranges, TypesPrerelease::Reject, "develop");
+        assert!(!develop.allow_prerelease);
+        assert!(develop.range.unwrap().satisfies(&"1.2.0".parse().unwrap()));
+    }
+
+    #[test]
+    fn types_versions_are_checked_against_the_registry_without_a_range() {
+        let policy = TypesPolicy::new(&[], TypesPrerelease::Allow, "master");
+
+        assert!(
+            check_types_compatibility(&built_with("0.9.0"), &built_with("0.9.1"), &policy).is_ok()
+        );
+        assert!(
+            check_types_compatibility(&built_with("0.9.0"), &built_with("0.8.0"), &policy).is_err()
+        );
+        assert!(
+            check_types_compatibility(&built_with("unknown"), &built_with("0.9.0"), &policy)
+                .is_ok()
+        );
+        assert!(
+            check_types_compatibility(&built_with("0.9.0"), &built_with("unknown"), &policy)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn types_prereleases_are_only_accepted_when_allowed() {
+        let ranges = parse_types_ranges("^1.0.0-alpha").unwrap();
+        let registry = built_with("0.9.0");
+        let repository = built_with("1.0.0-alpha.1");
+
+        let allow = TypesPolicy::new(&ranges, TypesPrerelease::Allow, "master");
+        assert!(check_types_compatibility(&registry, &repository, &allow).is_ok());
+
+        let reject = TypesPolicy::new(&ranges, TypesPrerelease::Reject, "master");
+        assert!(check_types_compatibility(&registry, &repository, &reject).is_err());
+    }
+
+    #[test]
+    fn published_sources_record_their_build_and_the_header_the_lowest_versions() {
+        let mut registry_source = source("B", "1.0.0");
+        registry_source["build"] = json!({
+            "toolchain": "0.8.5",
+            "types": "0.8.0",
+            "buildTime": "2023-01-01T00:00:00.000Z",
+            "repository": "paperback-community/other"
+        });
+        let mut registry_versioning = versioning(vec![registry_source]);
+
+        publish(
+            &mut registry_versioning,
+            &versioning(vec![source("A", "1.0.0")]),
+            &policy(MetadataPolicy::Warn, &[]),
+        )
+        .unwrap();
+
+        let build = registry_versioning.source("A").unwrap().build().unwrap();
+        assert_eq!(build.repository(), REPOSITORY);
+        assert_eq!(build.types, "0.9.0");
+        assert_eq!(registry_versioning.built_with.toolchain, "0.8.5");
+        assert_eq!(registry_versioning.built_with.types, "0.8.0");
+    }
+
+    #[test]
+    fn restored_sources_keep_the_archived_versions() {
+        let mut registry_source = source("A", "2.0.0");
+        registry_source["versions"] = json!(["2.0.0", "1.0.0"]);
+        let mut registry_versioning = versioning(vec![registry_source]);
+
+        registry_versioning.restore_source(serde_json::from_value(source("A", "1.0.0")).unwrap());
+
+        let restored_source = serde_json::to_value(registry_versioning.source("A")).unwrap();
+        assert_eq!(restored_source["version"], "1.0.0");
+        assert_eq!(restored_source["versions"], json!(["2.0.0", "1.0.0"]));
+    }
+
+    #[test]
+    fn restored_sources_are_classified_by_the_version_they_replace() {
+        let restored: Source = serde_json::from_value(source("A", "1.0.0")).unwrap();
+
+        assert_eq!(
+            SourceChange::restored(&restored, Some("2.0.0")).outcome,
+            Outcome::Downgraded { allowed: true }
+        );
+        assert_eq!(
+            SourceChange::restored(&restored, Some("0.9.0")).outcome,
+            Outcome::Updated
+        );
+        assert_eq!(
+            SourceChange::restored(&restored, None).outcome,
+            Outcome::Added
+        );
+    }
+
+    #[test]
+    fn adult_sources_are_split_off() {
+        let mut adult_source = source("B", "1.0.0");
+        adult_source["contentRating"] = json!("ADULT");
+
+        let (listed, adult) = versioning(vec![source("A", "1.0.0"), adult_source]).split_adult();
+
+        assert_eq!(
+            listed
+                .sources()
+                .iter()
+                .map(Source::id)
+                .collect::<Vec<&str>>(),
+            vec!["A"]
+        );
+        assert_eq!(
+            adult
+                .sources()
+                .iter()
+                .map(Source::id)
+                .collect::<Vec<&str>>(),
+            vec!["B"]
+        );
+    }
+
+    #[test]
+    fn capabilities_are_read_from_a_list_and_a_bitmask() {
+        let from_list: Capabilities = serde_json::from_str("[1, 4, 64]").unwrap();
+        let from_bitmask: Capabilities = serde_json::from_str("69").unwrap();
+
+        assert_eq!(from_list, from_bitmask);
+        assert_eq!(
+            from_list.names(),
+            vec!["Manga chapters", "Discover sections", "Manga search"]
+        );
+    }
+
+    #[test]
+    fn capabilities_are_written_as_a_sorted_list() {
+        let capabilities: Capabilities = serde_json::from_str("[64, 1, 4]").unwrap();
+
+        assert_eq!(serde_json::to_string(&capabilities).unwrap(), "[1,4,64]");
+        assert_eq!(
+            serde_json::from_str::<Capabilities>(&serde_json::to_string(&capabilities).unwrap())
+                .unwrap(),
+            capabilities
+        );
+    }
+
+    #[test]
+    fn capabilities_with_unknown_bits_are_rejected() {
+        assert!(serde_json::from_str::<Capabilities>("128").is_err());
+        assert!(serde_json::from_str::<Capabilities>("[1, 256]").is_err());
+    }
+
+    #[test]
+    fn types_ranges_without_a_branch_keep_their_comparators() {
+        let ranges = parse_types_ranges(">=0.9.0").unwrap();
+
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].0, None);
+        assert!(ranges[0].1.satisfies(&"0.9.1".parse().unwrap()));
+        assert!(!ranges[0].1.satisfies(&"0.8.9".parse().unwrap()));
+    }
+
+    #[test]
+    fn types_ranges_are_prefixed_by_their_branch() {
+        let ranges = parse_types_ranges("master=^0.9.0-alpha; 1.x || >=2").unwrap();
+
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].0.as_deref(), Some("master"));
+        assert!(ranges[0].1.satisfies(&"0.9.0-alpha.3".parse().unwrap()));
+        assert_eq!(ranges[1].0, None);
+        assert!(ranges[1].1.satisfies(&"1.4.0".parse().unwrap()));
+        assert!(ranges[1].1.satisfies(&"3.0.0".parse().unwrap()));
+        assert!(!ranges[1].1.satisfies(&"0.9.0".parse().unwrap()));
+    }
+
+    #[test]
+    fn types_ranges_which_can_not_be_parsed_are_rejected() {
+        assert!(parse_types_ranges("master=not a range").is_err());
+    }
+
+    #[test]
+    fn github_handles_are_read_from_mentions_and_profile_links() {
+        assert_eq!(github_handle("octocat"), "octocat");
+        assert_eq!(github_handle(" @octocat "), "octocat");
+        assert_eq!(github_handle("https://github.com/octocat/"), "octocat");
+        assert_eq!(github_handle("http://www.github.com/octocat"), "octocat");
+        assert!(!is_valid_github_handle(github_handle(
+            "https://gitlab.com/octocat"
+        )));
+    }
+
+    #[test]
+    fn sources_are_normalized() {
+        let mut source: Source = serde_json::from_str(
+            r##"{
+                "id": "Example",
+                "name": "Example",
+                "description": "An example source",
+                "version": "1.0.0",
+                "icon": "icon.png",
+                "language": "en",
+                "contentRating": "EVERYONE",
+                "badges": [
+                    null,
+                    { "label": "Official", "textColor": "white", "backgroundColor": "#123" },
+                    { "label": "Official", "textColor": "white", "backgroundColor": "#123" },
+                    { "label": "Fast", "textColor": "notacolor", "backgroundColor": "#12345" }
+                ],
+                "capabilities": 1,
+                "developers": [
+                    { "name": "Octo", "website": "github.com", "github": "https://github.com/octocat" },
+                    { "name": "Octo", "website": null, "github": "@octocat" },
+                    { "name": "", "website": null, "github": null }
+                ]
+            }"##,
+        )
+        .unwrap();
+
+        let changes = source.normalize();
+
+        assert_eq!(changes.len(), 9);
+        assert_eq!(
+            source
+                .badges()
+                .map(|badge| badge.label())
+                .collect::<Vec<&str>>(),
+            vec!["Official", "Fast"]
+        );
+
+        let fast = source.badges().nth(1).unwrap();
+        assert_eq!(fast.text_color, DEFAULT_BADGE_TEXT_COLOR);
+        assert_eq!(fast.background_color, DEFAULT_BADGE_BACKGROUND_COLOR);
+
+        let developers: Vec<&Developers> = source.developers().collect();
+        assert_eq!(developers.len(), 1);
+        assert_eq!(developers[0].github.as_deref(), Some("octocat"));
+        assert_eq!(developers[0].website, None);
+
+        // A normalized source is left unchanged
+        assert!(source.normalize().is_empty());
+    }
+}